@@ -1,24 +1,24 @@
 use std::sync::Arc;
 
-use axum::{Extension, Json, extract::State, response::IntoResponse};
-use serde::{Deserialize, Serialize};
+use axum::{Extension, Json, response::IntoResponse};
+use serde::Serialize;
 
-use crate::{
-    api::Result,
-    extractor::{RouterClient, UserSession},
-    service::AuthService,
-};
+use crate::{api::Result, extractor::UserSession, service::AuthService};
 
 #[derive(Serialize)]
 pub struct PostResponseBody {
     status: String,
 }
 
+pub async fn handle() -> Result<PostResponseBody> {
+    Ok(PostResponseBody {
+        status: "OK".to_owned(),
+    })
+}
+
 pub async fn post(
     _user_session: UserSession, // Force an authenticated user
-    Extension(auth_service): Extension<Arc<AuthService>>,
+    Extension(_auth_service): Extension<Arc<AuthService>>,
 ) -> Result<impl IntoResponse> {
-    Ok(Json(PostResponseBody {
-        status: "OK".to_owned(),
-    }))
+    Ok(Json(handle().await?))
 }
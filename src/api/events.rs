@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension,
+    extract::Query,
+    http::HeaderMap,
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use futures_util::{StreamExt, stream};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    api::{Result, net::resolve_namespace},
+    error::Error,
+    service::{AuthService, NetlinkService, NetworkEvent, NetworkEventId, SessionId},
+};
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    pub netns: Option<String>,
+    // Browsers cannot set headers on an EventSource, so they pass the
+    // session token here instead
+    pub token: Option<String>,
+}
+
+fn to_sse_event(
+    id: Option<NetworkEventId>,
+    event: &NetworkEvent,
+) -> core::result::Result<Event, axum::Error> {
+    let sse_event = Event::default().json_data(event)?;
+    Ok(match id {
+        Some(id) => sse_event.id(id.to_string()),
+        None => sse_event,
+    })
+}
+
+pub async fn get(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Query(EventsQuery { netns, token }): Query<EventsQuery>,
+    Extension(auth_service): Extension<Arc<AuthService>>,
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse> {
+    // Force an authenticated user
+    let token = match &bearer {
+        Some(TypedHeader(bearer)) => Some(bearer.token()),
+        None => token.as_deref(),
+    };
+    let session_id: SessionId = token
+        .and_then(|x| x.try_into().ok())
+        .ok_or(Error::Unauthenticated)?;
+    auth_service.validate_session(session_id)?;
+
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    // Sent by clients that are reconnecting to the stream
+    let last_event_id = headers
+        .get("Last-Event-ID")
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.parse::<NetworkEventId>().ok());
+    let subscription = netlink_service
+        .subscribe_network_events(last_event_id)
+        .map_err(|_| Error::Unexpected)?;
+
+    let mut backlog = vec![];
+    if subscription.truncated {
+        backlog.push(to_sse_event(None, &NetworkEvent::ResyncRequired));
+    }
+    for (id, event) in subscription.backlog.iter() {
+        backlog.push(to_sse_event(Some(*id), event));
+    }
+
+    let live = stream::unfold(subscription.receiver, |mut receiver| async move {
+        let sse_event = match receiver.recv().await {
+            Ok((id, event)) => to_sse_event(Some(id), &event),
+            Err(RecvError::Lagged(count)) => {
+                log::debug!("Event stream subscriber missed {} events", count);
+                to_sse_event(None, &NetworkEvent::ResyncRequired)
+            }
+            Err(RecvError::Closed) => return None,
+        };
+        Some((sse_event, receiver))
+    });
+
+    Ok(Sse::new(stream::iter(backlog).chain(live)).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,18 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, http::StatusCode, response::IntoResponse};
+
+use crate::{api::Result, service::NetlinkService};
+
+// Left unauthenticated, so that it can be polled by monitoring tools
+pub async fn get(
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+) -> Result<impl IntoResponse> {
+    let health = netlink_service.get_health();
+    let status_code = match health.healthy {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    Ok((status_code, Json(health)))
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use axum::{Extension, Json, extract::State, response::IntoResponse};
+use axum::{Extension, Json, response::IntoResponse};
 use serde::{Deserialize, Serialize};
 
 use crate::{api::Result, extractor::RouterClient, service::AuthService};
@@ -12,14 +12,14 @@ pub struct PostRequestBody {
 
 #[derive(Serialize)]
 pub struct PostResponseBody {
-    auth_token: String,
+    pub auth_token: String,
 }
 
-pub async fn post(
-    router_client: RouterClient,
-    Extension(auth_service): Extension<Arc<AuthService>>,
-    Json(PostRequestBody { password }): Json<PostRequestBody>,
-) -> Result<impl IntoResponse> {
+pub async fn handle(
+    router_client: &RouterClient,
+    auth_service: &AuthService,
+    PostRequestBody { password }: PostRequestBody,
+) -> Result<PostResponseBody> {
     log::info!(
         "Router client '{}' (MAC: {}) attemping sign in...",
         router_client.ip_address,
@@ -28,7 +28,15 @@ pub async fn post(
     let session_id = auth_service.sign_in(password)?.to_string();
     log::info!("New session created: {}", session_id);
 
-    Ok(Json(PostResponseBody {
+    Ok(PostResponseBody {
         auth_token: session_id,
-    }))
+    })
+}
+
+pub async fn post(
+    router_client: RouterClient,
+    Extension(auth_service): Extension<Arc<AuthService>>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(handle(&router_client, &auth_service, payload).await?))
 }
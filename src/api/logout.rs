@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
-use axum::{Extension, Json, extract::State, response::IntoResponse};
-use serde::{Deserialize, Serialize};
+use axum::{Extension, Json, response::IntoResponse};
+use serde::Serialize;
 
 use crate::{
     api::Result,
@@ -14,11 +14,11 @@ pub struct PostResponseBody {
     result: String,
 }
 
-pub async fn post(
-    router_client: RouterClient,
-    user_session: UserSession, // Force an authenticated user
-    Extension(auth_service): Extension<Arc<AuthService>>,
-) -> Result<impl IntoResponse> {
+pub async fn handle(
+    router_client: &RouterClient,
+    user_session: &UserSession,
+    auth_service: &AuthService,
+) -> Result<PostResponseBody> {
     log::info!(
         "Router client '{}' (MAC: {}) signed out with session '{}'",
         router_client.ip_address,
@@ -27,7 +27,17 @@ pub async fn post(
     );
     auth_service.sign_out()?;
 
-    Ok(Json(PostResponseBody {
+    Ok(PostResponseBody {
         result: "OK".to_owned(),
-    }))
+    })
+}
+
+pub async fn post(
+    router_client: RouterClient,
+    user_session: UserSession, // Force an authenticated user
+    Extension(auth_service): Extension<Arc<AuthService>>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(
+        handle(&router_client, &user_session, &auth_service).await?,
+    ))
 }
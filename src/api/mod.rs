@@ -1,9 +1,12 @@
 use crate::error::Error;
 
 pub mod auth_status;
+pub mod events;
+pub mod health;
 pub mod login;
 pub mod logout;
 pub mod net;
+pub mod ws;
 
 // Result for all endpoints that can fail
 pub type Result<T> = core::result::Result<T, Error>;
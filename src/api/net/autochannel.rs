@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{
+        ChannelRecommendation, NetlinkChannel, NetlinkChannelWidth, NetlinkService, PhyBandKind,
+        WirelessRefusal, center_frequencies,
+    },
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    interface_name: String,
+    // All bands of the device if missing
+    band: Option<PhyBandKind>,
+    // Move the interface to the recommended channel, as wide as its current
+    // one if possible. Requires the band, unless the interface is already
+    // operating.
+    #[serde(default)]
+    apply: bool,
+}
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    bands: Vec<ChannelRecommendation>,
+    // Set when the recommendation was applied
+    channel: Option<NetlinkChannel>,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+    if interface.phy_index.is_none() {
+        return Err(Error::InterfaceNotWireless);
+    }
+
+    let mut bands = netlink_service
+        .recommend_channels(&interface)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to survey channels: {}", e);
+            Error::from_netlink(&e, Error::ScanFailed)
+        })?;
+
+    // The band of the current channel is kept when applying
+    let band = payload.band.or_else(|| {
+        let frequency = interface.channel?.frequency;
+        bands
+            .iter()
+            .find(|x| x.channels.iter().any(|x| x.frequency == frequency))
+            .map(|x| x.band)
+    });
+    if let Some(band) = payload.band {
+        bands.retain(|x| x.band == band);
+    }
+
+    if !payload.apply {
+        return Ok(PostResponseBody {
+            bands,
+            channel: None,
+        });
+    }
+
+    let frequency = bands
+        .iter()
+        .find(|x| band.is_some_and(|band| x.band == band))
+        .and_then(|x| x.recommended.as_ref())
+        .map(|x| x.frequency)
+        .ok_or(Error::ChannelNotSupported)?;
+
+    // The current width is kept where the new channel allows it, otherwise
+    // the interface falls back to HT20
+    let mut channels = vec![];
+    if let Some(current) = interface.channel {
+        for center in center_frequencies(frequency, current.width.mhz()) {
+            let center2 = match current.width {
+                NetlinkChannelWidth::Mhz80Plus80 => match current.center_frequency2 {
+                    Some(center2) if center2.abs_diff(center) > 80 => Some(center2),
+                    _ => continue,
+                },
+                _ => None,
+            };
+            channels.push((current.width, Some(center), center2));
+        }
+    }
+    channels.push((NetlinkChannelWidth::Mhz(20), None, None));
+
+    for (width, center_frequency, center_frequency2) in channels {
+        let result = netlink_service
+            .set_channel(
+                &interface,
+                frequency,
+                width,
+                center_frequency,
+                center_frequency2,
+            )
+            .await;
+        match result {
+            Ok(()) => break,
+            Err(e) if e.downcast_ref() == Some(&WirelessRefusal::UnsupportedChannel) => {
+                log::debug!(
+                    "Channel {:?} wide at {} MHz is not usable",
+                    width,
+                    frequency
+                );
+            }
+            Err(e) => {
+                log::error!("Failed to set channel: {}", e);
+                return Err(Error::from_netlink(&e, Error::WirelessSettingsFailed));
+            }
+        }
+    }
+
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+
+    Ok(PostResponseBody {
+        bands,
+        channel: interface.channel,
+    })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{BridgeOptions, NetlinkService, RouteInterfaceKind},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    bridge_name: String,
+    options: BridgeOptions,
+}
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    options: Option<BridgeOptions>,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    let bridge = netlink_service
+        .find_interface_by_name(&payload.bridge_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+
+    if !matches!(bridge.kind, RouteInterfaceKind::Bridge) {
+        return Err(Error::InterfaceNotBridge);
+    }
+
+    netlink_service
+        .set_bridge_options(&bridge, &payload.options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to set bridge options: {}", e);
+            Error::from_netlink(&e, Error::Unexpected)
+        })?;
+
+    let bridge = netlink_service
+        .find_interface_by_name(&payload.bridge_name)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to find bridge after setting options: {}", e);
+            Error::Unexpected
+        })?;
+
+    Ok(PostResponseBody {
+        options: bridge.bridge_options,
+    })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
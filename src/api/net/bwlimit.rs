@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkService, Qdisc, TrafficDirection, TrafficShaper},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    interface_name: String,
+    direction: TrafficDirection,
+    // Removes the shaper of the direction when missing
+    shaper: Option<TrafficShaper>,
+}
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    // Qdiscs of the interface, and of the device shaping its ingress traffic
+    qdiscs: Vec<Qdisc>,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+
+    if payload.shaper.as_ref().and_then(TrafficShaper::rate) == Some(0) {
+        return Err(Error::InvalidBandwidthLimit);
+    }
+
+    netlink_service
+        .set_interface_shaper(&interface, payload.direction, payload.shaper)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to set traffic shaper: {}", e);
+            Error::from_netlink(&e, Error::TrafficShapingFailed)
+        })?;
+
+    let ingress_shaper_name = netlink_service.get_ingress_shaper_name(&interface);
+    let qdiscs = netlink_service
+        .get_qdiscs()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get qdiscs after setting traffic shaper: {}", e);
+            Error::Unexpected
+        })?
+        .into_iter()
+        .filter(|x| {
+            x.interface_name.as_deref() == Some(&interface.name)
+                || x.interface_name.as_deref() == Some(&ingress_shaper_name)
+        })
+        .collect();
+
+    Ok(PostResponseBody { qdiscs })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkChannel, NetlinkChannelWidth, NetlinkService},
+};
+
+// The channel is given by its frequency in MHz, or by its number
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    interface_name: String,
+    frequency: Option<u32>,
+    channel: Option<u32>,
+    // 20 MHz if missing
+    width: Option<NetlinkChannelWidth>,
+    // Required for channels wider than 20 MHz
+    center_frequency: Option<u32>,
+    center_frequency2: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    channel: Option<NetlinkChannel>,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+    let Some(phy_index) = interface.phy_index else {
+        return Err(Error::InterfaceNotWireless);
+    };
+
+    let frequency = match (payload.frequency, payload.channel) {
+        (Some(frequency), None) => frequency,
+        (None, Some(channel)) => {
+            let phys = netlink_service.get_phys().await.map_err(|e| {
+                log::error!("Failed to get wireless devices: {}", e);
+                Error::from_netlink(&e, Error::Unexpected)
+            })?;
+            phys.iter()
+                .find(|x| x.index == phy_index)
+                .and_then(|x| x.channel_frequency(channel))
+                .ok_or(Error::ChannelNotSupported)?
+        }
+        _ => return Err(Error::InvalidChannel),
+    };
+
+    netlink_service
+        .set_channel(
+            &interface,
+            frequency,
+            payload.width.unwrap_or(NetlinkChannelWidth::NoHt20),
+            payload.center_frequency,
+            payload.center_frequency2,
+        )
+        .await
+        .map_err(|e| {
+            log::error!("Failed to set channel: {}", e);
+            Error::from_netlink(&e, Error::WirelessSettingsFailed)
+        })?;
+
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+
+    Ok(PostResponseBody {
+        channel: interface.channel,
+    })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
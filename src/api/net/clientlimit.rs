@@ -0,0 +1,66 @@
+use std::{str::FromStr, sync::Arc};
+
+use axum::{Extension, Json, response::IntoResponse};
+use macaddr::MacAddr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::Result,
+    error::Error,
+    extractor::UserSession,
+    service::{ClientLimit, ClientLimitService, ClientLimitStatus},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    mac_address: String,
+    // Caps in kbit/s, the limit of the client is removed when both are missing
+    upload: Option<u64>,
+    download: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponseBody {
+    client_limits: Vec<ClientLimitStatus>,
+}
+
+pub async fn handle(
+    client_limit_service: &ClientLimitService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    let mac_address =
+        MacAddr::from_str(&payload.mac_address).map_err(|_| Error::InvalidMacAddress)?;
+
+    if payload.upload == Some(0) || payload.download == Some(0) {
+        return Err(Error::InvalidBandwidthLimit);
+    }
+
+    let limit = ClientLimit {
+        upload: payload.upload,
+        download: payload.download,
+    };
+    client_limit_service
+        .set_limit(mac_address, limit)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to set client limit: {}", e);
+            Error::from_netlink(&e, Error::ClientLimitFailed)
+        })?;
+
+    let client_limits = client_limit_service.get_limits().await.map_err(|e| {
+        log::error!("Failed to get client limits after setting one: {}", e);
+        Error::Unexpected
+    })?;
+
+    Ok(PostResponseBody { client_limits })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(client_limit_service): Extension<Arc<ClientLimitService>>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(handle(&client_limit_service, payload).await?))
+}
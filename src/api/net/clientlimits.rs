@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, response::IntoResponse};
+use serde::Serialize;
+
+use crate::{
+    api::Result,
+    error::Error,
+    extractor::UserSession,
+    service::{ClientLimitService, ClientLimitStatus},
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponseBody {
+    client_limits: Vec<ClientLimitStatus>,
+}
+
+pub async fn handle(client_limit_service: &ClientLimitService) -> Result<PostResponseBody> {
+    let client_limits = client_limit_service.get_limits().await.map_err(|e| {
+        log::error!("Failed to get client limits: {}", e);
+        Error::from_netlink(&e, Error::Unexpected)
+    })?;
+
+    Ok(PostResponseBody { client_limits })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(client_limit_service): Extension<Arc<ClientLimitService>>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(handle(&client_limit_service).await?))
+}
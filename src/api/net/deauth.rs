@@ -0,0 +1,95 @@
+use std::{str::FromStr, sync::Arc};
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use chrono::Duration;
+use macaddr::MacAddr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkInterfaceMode, NetlinkService},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    interface_name: String,
+    mac_address: String,
+    // IEEE 802.11 reason code sent to the station
+    reason_code: Option<u16>,
+    // Keep the station from reconnecting for this long
+    block_seconds: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    result: String,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    let mac_address =
+        MacAddr::from_str(&payload.mac_address).map_err(|_| Error::InvalidMacAddress)?;
+
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+    match &interface.mode_status {
+        Some(mode_status)
+            if matches!(
+                mode_status.active,
+                NetlinkInterfaceMode::AccessPoint | NetlinkInterfaceMode::P2pGo
+            ) => {}
+        Some(_) => return Err(Error::InterfaceNotAccessPoint),
+        None => return Err(Error::InterfaceNotWireless),
+    }
+
+    let stations = netlink_service
+        .get_stations(&interface)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get stations: {}", e);
+            Error::from_netlink(&e, Error::Unexpected)
+        })?;
+    if !stations
+        .iter()
+        .any(|x| x.mac_address == mac_address.to_string())
+    {
+        return Err(Error::StationNotFound);
+    }
+
+    let block = payload
+        .block_seconds
+        .filter(|x| *x > 0)
+        .map(|x| Duration::seconds(x.into()));
+    netlink_service
+        .deauthenticate_station(&interface, mac_address, payload.reason_code, block)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to deauthenticate station: {}", e);
+            Error::from_netlink(&e, Error::Unexpected)
+        })?;
+
+    Ok(PostResponseBody {
+        result: "OK".to_owned(),
+    })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
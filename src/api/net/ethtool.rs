@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{EthtoolInfo, EthtoolSettings, NetlinkService},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    interface_name: String,
+    settings: EthtoolSettings,
+}
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    ethtool: EthtoolInfo,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+
+    let ethtool = netlink_service
+        .get_interface_ethtool(&interface)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get ethtool information: {}", e);
+            Error::from_netlink(&e, Error::Unexpected)
+        })?;
+
+    // Fixed features would be silently ignored by the kernel
+    for name in payload.settings.features.keys() {
+        if !ethtool
+            .features
+            .iter()
+            .any(|feature| &feature.name == name && feature.changeable)
+        {
+            return Err(Error::FeatureNotChangeable);
+        }
+    }
+
+    netlink_service
+        .set_interface_ethtool(&interface, &payload.settings)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to set ethtool settings: {}", e);
+            Error::from_netlink(&e, Error::EthtoolSettingsFailed)
+        })?;
+
+    let ethtool = netlink_service
+        .get_interface_ethtool(&interface)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get ethtool information after setting it: {}", e);
+            Error::Unexpected
+        })?;
+
+    Ok(PostResponseBody { ethtool })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkInterfaceMode, NetlinkService, RouteInterfaceKind},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    interface_name: String,
+    // Bridge to attach the interface to, or none to release it
+    bridge_name: Option<String>,
+    // Enable 4-address mode on station interfaces before bridging them
+    #[serde(default)]
+    enable_4addr: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponseBody {
+    bridge_name: Option<String>,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+
+    let bridge = match payload.bridge_name {
+        Some(bridge_name) => {
+            let bridge = netlink_service
+                .find_interface_by_name(&bridge_name)
+                .await
+                .map_err(|_| Error::InterfaceNotFound)?;
+            if !matches!(bridge.kind, RouteInterfaceKind::Bridge) {
+                return Err(Error::InterfaceNotBridge);
+            }
+            Some(bridge)
+        }
+        None => None,
+    };
+
+    // Station mode interfaces can't be bridged unless they use 4-address frames
+    let mut enabled_4addr = false;
+    if bridge.is_some()
+        && let Some(mode_status) = &interface.mode_status
+        && matches!(mode_status.active, NetlinkInterfaceMode::Station)
+        && !mode_status.use_4addr
+    {
+        if !payload.enable_4addr {
+            return Err(Error::StationRequires4Addr);
+        }
+
+        netlink_service
+            .set_interface_4addr(&interface, true)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to enable 4-address mode: {}", e);
+                Error::from_netlink(&e, Error::StationRequires4Addr)
+            })?;
+        enabled_4addr = true;
+    }
+
+    if let Err(e) = netlink_service
+        .set_interface_controller(&interface, bridge.as_ref())
+        .await
+    {
+        log::error!("Failed to set interface bridge: {}", e);
+        // Left as it was found, since it won't be bridged after all
+        if enabled_4addr
+            && let Err(e) = netlink_service.set_interface_4addr(&interface, false).await
+        {
+            log::error!("Failed to disable 4-address mode again: {}", e);
+        }
+        return Err(Error::from_netlink(&e, Error::Unexpected));
+    }
+
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to find interface after setting bridge: {}", e);
+            Error::Unexpected
+        })?;
+
+    Ok(PostResponseBody {
+        bridge_name: interface.controller,
+    })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
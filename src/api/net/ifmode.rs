@@ -1,16 +1,16 @@
 use std::sync::Arc;
 
-use axum::{Extension, Json, extract::State, response::IntoResponse};
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    api::Result,
-    error::Error,
-    extractor::{RouterClient, UserSession},
-    service::{
-        AuthService, LinkFlagsStruct, LinkState, NetlinkInterface, NetlinkInterfaceMode,
-        NetlinkService,
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
     },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkInterfaceMode, NetlinkService},
 };
 
 #[derive(Deserialize)]
@@ -26,11 +26,10 @@ pub struct PostResponseBody {
     interface_mode: NetlinkInterfaceMode,
 }
 
-pub async fn post(
-    _user_session: UserSession, // Force an authenticated user
-    Extension(netlink_service): Extension<Arc<NetlinkService>>,
-    Json(payload): Json<PostRequestBody>,
-) -> Result<impl IntoResponse> {
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
     let interface = netlink_service
         .find_interface_by_name(&payload.interface_name)
         .await
@@ -41,7 +40,7 @@ pub async fn post(
         .await
         .map_err(|e| {
             log::error!("Failed to set interface mode: {}", e);
-            Error::UnexpectedError
+            Error::from_netlink(&e, Error::Unexpected)
         })?;
 
     let interface = netlink_service
@@ -49,13 +48,24 @@ pub async fn post(
         .await
         .map_err(|e| {
             log::error!("Failed to find interface after setting mode: {}", e);
-            Error::UnexpectedError
+            Error::Unexpected
         })?;
 
-    Ok(Json(PostResponseBody {
+    Ok(PostResponseBody {
         interface_mode: interface
             .mode_status
             .map(|x| x.active)
-            .ok_or(Error::UnexpectedError)?,
-    }))
+            .ok_or(Error::Unexpected)?,
+    })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
 }
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkInterface, NetlinkService},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    interface_name: String,
+    new_name: String,
+}
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    interface: NetlinkInterface,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+
+    if netlink_service
+        .find_interface_by_name(&payload.new_name)
+        .await
+        .is_ok()
+    {
+        return Err(Error::InterfaceAlreadyExists);
+    }
+
+    netlink_service
+        .rename_interface(&interface, &payload.new_name)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to rename interface: {}", e);
+            Error::from_netlink(&e, Error::InterfaceRenameFailed)
+        })?;
+
+    let interface = netlink_service
+        .find_interface_by_name(&payload.new_name)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to find interface after renaming it: {}", e);
+            Error::Unexpected
+        })?;
+
+    Ok(PostResponseBody { interface })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkService, namespace_path},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    interface_name: String,
+    // Moves the interface back into the namespace of the server if missing
+    target_netns: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    result: String,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    // Make sure that the target namespace exists before moving anything
+    if let Some(target_netns) = &payload.target_netns {
+        namespace_path(target_netns).map_err(|e| {
+            log::warn!("Failed to resolve network namespace: {}", e);
+            Error::NamespaceNotFound
+        })?;
+    }
+
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+
+    netlink_service
+        .set_interface_namespace(&interface, payload.target_netns.as_deref())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to move interface to network namespace: {}", e);
+            Error::from_netlink(&e, Error::InterfaceMoveFailed)
+        })?;
+
+    Ok(PostResponseBody {
+        result: "OK".to_owned(),
+    })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
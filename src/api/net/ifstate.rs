@@ -1,13 +1,16 @@
 use std::sync::Arc;
 
-use axum::{Extension, Json, extract::State, response::IntoResponse};
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    api::Result,
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
     error::Error,
-    extractor::{RouterClient, UserSession},
-    service::{AuthService, LinkState, NetlinkInterface, NetlinkService},
+    extractor::UserSession,
+    service::{LinkState, NetlinkService},
 };
 
 #[derive(Deserialize)]
@@ -21,11 +24,10 @@ pub struct PostResponseBody {
     link_state: LinkState,
 }
 
-pub async fn post(
-    _user_session: UserSession, // Force an authenticated user
-    Extension(netlink_service): Extension<Arc<NetlinkService>>,
-    Json(payload): Json<PostRequestBody>,
-) -> Result<impl IntoResponse> {
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
     let interface = netlink_service
         .find_interface_by_name(&payload.interface_name)
         .await
@@ -34,14 +36,25 @@ pub async fn post(
     netlink_service
         .set_interface_state(&interface, payload.link_state)
         .await
-        .map_err(|_| Error::UnexpectedError)?;
+        .map_err(|e| Error::from_netlink(&e, Error::Unexpected))?;
 
     let interface = netlink_service
         .find_interface_by_name(&payload.interface_name)
         .await
-        .map_err(|_| Error::UnexpectedError)?;
+        .map_err(|_| Error::Unexpected)?;
+
+    Ok(PostResponseBody {
+        link_state: interface.state().ok_or(Error::Unexpected)?,
+    })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
 
-    Ok(Json(PostResponseBody {
-        link_state: interface.state(),
-    }))
+    Ok(Json(handle(&netlink_service, payload).await?))
 }
@@ -1,13 +1,16 @@
 use std::sync::Arc;
 
-use axum::{Extension, Json, extract::State, response::IntoResponse};
-use serde::{Deserialize, Serialize};
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::Serialize;
 
 use crate::{
-    api::Result,
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
     error::Error,
-    extractor::{RouterClient, UserSession},
-    service::{AuthService, NetlinkInterface, NetlinkService},
+    extractor::UserSession,
+    service::{NetlinkInterface, NetlinkService},
 };
 
 #[derive(Serialize)]
@@ -15,14 +18,21 @@ pub struct PostResponseBody {
     interfaces: Vec<NetlinkInterface>,
 }
 
+pub async fn handle(netlink_service: &NetlinkService) -> Result<PostResponseBody> {
+    let interfaces = netlink_service
+        .get_interfaces()
+        .await
+        .map_err(|_| Error::Unexpected)?;
+
+    Ok(PostResponseBody { interfaces })
+}
+
 pub async fn post(
     _user_session: UserSession, // Force an authenticated user
     Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
 ) -> Result<impl IntoResponse> {
-    let interfaces = netlink_service
-        .get_interfaces()
-        .await
-        .map_err(|_| Error::UnexpectedError)?;
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
 
-    Ok(Json(PostResponseBody { interfaces }))
+    Ok(Json(handle(&netlink_service).await?))
 }
@@ -1,3 +1,49 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::{api::Result, error::Error, service::NetlinkService};
+
+pub mod autochannel;
+pub mod brconfig;
+pub mod bwlimit;
+pub mod channel;
+pub mod clientlimit;
+pub mod clientlimits;
+pub mod deauth;
+pub mod ethtool;
+pub mod ifcreate;
+pub mod ifdelete;
+pub mod ifdetail;
+pub mod ifmaster;
 pub mod ifmode;
+pub mod ifname;
+pub mod ifnetns;
 pub mod ifstate;
 pub mod interfaces;
+pub mod netns;
+pub mod phys;
+pub mod qdiscs;
+pub mod regcountry;
+pub mod regdomain;
+pub mod scan;
+pub mod stations;
+pub mod traffic;
+pub mod txpower;
+
+// Query string of the endpoints that operate on interfaces. Without a
+// namespace, they operate in the namespace of the server itself.
+#[derive(Deserialize)]
+pub struct NetnsQuery {
+    pub netns: Option<String>,
+}
+
+pub async fn resolve_namespace(
+    netlink_service: &Arc<NetlinkService>,
+    netns: Option<&str>,
+) -> Result<Arc<NetlinkService>> {
+    netlink_service.namespace(netns).await.map_err(|e| {
+        log::warn!("Failed to resolve network namespace: {}", e);
+        Error::NamespaceNotFound
+    })
+}
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, response::IntoResponse};
+use serde::Serialize;
+
+use crate::{api::Result, error::Error, extractor::UserSession, service::NetlinkService};
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    namespaces: Vec<String>,
+}
+
+pub async fn handle(netlink_service: &NetlinkService) -> Result<PostResponseBody> {
+    let namespaces = netlink_service.list_namespaces().map_err(|e| {
+        log::error!("Failed to list network namespaces: {}", e);
+        Error::Unexpected
+    })?;
+
+    Ok(PostResponseBody { namespaces })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(handle(&netlink_service).await?))
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::Serialize;
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkPhy, NetlinkService},
+};
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    phys: Vec<NetlinkPhy>,
+}
+
+pub async fn handle(netlink_service: &NetlinkService) -> Result<PostResponseBody> {
+    let phys = netlink_service.get_phys().await.map_err(|e| {
+        log::error!("Failed to get wireless devices: {}", e);
+        Error::from_netlink(&e, Error::Unexpected)
+    })?;
+
+    Ok(PostResponseBody { phys })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service).await?))
+}
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkService, Qdisc},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    // Only list the qdiscs of this interface
+    interface_name: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    qdiscs: Vec<Qdisc>,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    if let Some(interface_name) = &payload.interface_name {
+        netlink_service
+            .find_interface_by_name(interface_name)
+            .await
+            .map_err(|_| Error::InterfaceNotFound)?;
+    }
+
+    let qdiscs = netlink_service.get_qdiscs().await.map_err(|e| {
+        log::error!("Failed to get qdiscs: {}", e);
+        Error::from_netlink(&e, Error::Unexpected)
+    })?;
+
+    let qdiscs = qdiscs
+        .into_iter()
+        .filter(|x| payload.interface_name.is_none() || x.interface_name == payload.interface_name)
+        .collect();
+
+    Ok(PostResponseBody { qdiscs })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
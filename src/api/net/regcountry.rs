@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkRegulatoryDomain, NetlinkService, is_valid_country},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    // ISO 3166-1 alpha-2 code, or "00" for the world domain
+    country: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponseBody {
+    regulatory_domain: NetlinkRegulatoryDomain,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    let country = payload.country.to_ascii_uppercase();
+    if !is_valid_country(&country) {
+        return Err(Error::InvalidCountryCode);
+    }
+
+    netlink_service
+        .set_regulatory_country(&country)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to set regulatory domain: {}", e);
+            Error::from_netlink(&e, Error::RegulatoryChangeFailed)
+        })?;
+
+    let regulatory_domain = netlink_service.get_regulatory_domain().await.map_err(|e| {
+        log::error!("Failed to get regulatory domain: {}", e);
+        Error::from_netlink(&e, Error::Unexpected)
+    })?;
+    // Reported as applied, which isn't always what was requested
+    if regulatory_domain.country != country {
+        log::warn!(
+            "Regulatory domain is {} rather than the requested {}",
+            regulatory_domain.country,
+            country
+        );
+    }
+
+    Ok(PostResponseBody { regulatory_domain })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::Serialize;
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkRegulatoryDomain, NetlinkService},
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponseBody {
+    regulatory_domain: NetlinkRegulatoryDomain,
+}
+
+pub async fn handle(netlink_service: &NetlinkService) -> Result<PostResponseBody> {
+    let regulatory_domain = netlink_service.get_regulatory_domain().await.map_err(|e| {
+        log::error!("Failed to get regulatory domain: {}", e);
+        Error::from_netlink(&e, Error::Unexpected)
+    })?;
+
+    Ok(PostResponseBody { regulatory_domain })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service).await?))
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Query, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        Result,
+        net::{NetnsQuery, resolve_namespace},
+    },
+    error::Error,
+    extractor::UserSession,
+    service::{NetlinkBss, NetlinkService},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequestBody {
+    interface_name: String,
+    // Only look for these networks
+    #[serde(default)]
+    ssids: Vec<String>,
+    // Only scan these frequencies, in MHz
+    #[serde(default)]
+    frequencies: Vec<u32>,
+}
+
+#[derive(Serialize)]
+pub struct PostResponseBody {
+    networks: Vec<NetlinkBss>,
+}
+
+pub async fn handle(
+    netlink_service: &NetlinkService,
+    payload: PostRequestBody,
+) -> Result<PostResponseBody> {
+    let interface = netlink_service
+        .find_interface_by_name(&payload.interface_name)
+        .await
+        .map_err(|_| Error::InterfaceNotFound)?;
+    if interface.mode_status.is_none() {
+        return Err(Error::InterfaceNotWireless);
+    }
+
+    let networks = netlink_service
+        .scan(&interface, payload.ssids, payload.frequencies)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to scan: {}", e);
+            Error::from_netlink(&e, Error::ScanFailed)
+        })?;
+
+    Ok(PostResponseBody { networks })
+}
+
+pub async fn post(
+    _user_session: UserSession, // Force an authenticated user
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Query(NetnsQuery { netns }): Query<NetnsQuery>,
+    Json(payload): Json<PostRequestBody>,
+) -> Result<impl IntoResponse> {
+    let netlink_service = resolve_namespace(&netlink_service, netns.as_deref()).await?;
+
+    Ok(Json(handle(&netlink_service, payload).await?))
+}
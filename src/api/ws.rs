@@ -0,0 +1,517 @@
+use std::{future, sync::Arc};
+
+use axum::{
+    Extension,
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+
+use crate::{
+    api::{self, Result},
+    error::Error,
+    extractor::{RouterClient, UserSession},
+    service::{
+        AuthService, ClientLimitService, NetlinkService, NetworkEvent, NetworkEventId, SessionId,
+    },
+};
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+// Methods that change the session of the connection, and so are handled one
+// after the other. Everything else runs on its own, so that a slow call
+// holds up neither other calls nor event notifications.
+const SESSION_METHODS: [&str; 4] = ["authenticate", "login", "logout", "authStatus"];
+// Responses of calls that are still to be sent
+const RESPONSE_CAPACITY: usize = 16;
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    // Requests without an ID are notifications, which get no response
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+// API errors keep their HTTP status code as the JSON-RPC error code
+impl From<Error> for RpcError {
+    fn from(value: Error) -> Self {
+        Self {
+            code: value.status_code().as_u16().into(),
+            message: value.message().to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RpcNotification<'a> {
+    jsonrpc: &'static str,
+    method: &'static str,
+    params: EventParams<'a>,
+}
+
+#[derive(Serialize)]
+struct EventParams<'a> {
+    id: Option<NetworkEventId>,
+    event: &'a NetworkEvent,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthenticateParams {
+    auth_token: String,
+    // Replay the events that were missed since this one
+    last_event_id: Option<NetworkEventId>,
+}
+
+#[derive(Serialize)]
+struct AuthenticateResult {
+    status: String,
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> core::result::Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError {
+        code: INVALID_PARAMS,
+        message: e.to_string(),
+    })
+}
+
+fn to_rpc_result<T: Serialize>(result: Result<T>) -> core::result::Result<Value, RpcError> {
+    let result = result?;
+    serde_json::to_value(result).map_err(|_| Error::Unexpected.into())
+}
+
+struct RpcConnection {
+    router_client: RouterClient,
+    auth_service: Arc<AuthService>,
+    netlink_service: Arc<NetlinkService>,
+    client_limit_service: Arc<ClientLimitService>,
+    session_id: Option<SessionId>,
+    // Event notifications are only sent to authenticated clients
+    events: Option<broadcast::Receiver<(NetworkEventId, NetworkEvent)>>,
+    // Notifications to send once the current response is out
+    pending: Vec<String>,
+    responses: mpsc::Sender<String>,
+}
+
+impl RpcConnection {
+    async fn run(mut self, mut socket: WebSocket, mut finished: mpsc::Receiver<String>) {
+        log::debug!(
+            "Router client '{}' opened a WebSocket connection",
+            self.router_client.ip_address
+        );
+
+        'connection: loop {
+            let events = self.events.as_mut();
+            let next_event = async move {
+                match events {
+                    Some(events) => events.recv().await,
+                    None => future::pending().await,
+                }
+            };
+
+            let outgoing = tokio::select! {
+                message = socket.recv() => match message {
+                    Some(Ok(Message::Text(text))) => self.handle_message(text.as_str()).await,
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        log::debug!("WebSocket connection failed: {}", e);
+                        break;
+                    }
+                },
+                Some(response) = finished.recv() => Some(response),
+                event = next_event => match event {
+                    // Stop notifying clients whose session is no longer valid
+                    Ok(_) if self.user_session().is_err() => continue,
+                    Ok((id, event)) => Self::notification(Some(id), &event),
+                    Err(RecvError::Lagged(count)) => {
+                        log::debug!("WebSocket subscriber missed {} events", count);
+                        Self::notification(None, &NetworkEvent::ResyncRequired)
+                    }
+                    Err(RecvError::Closed) => {
+                        self.events = None;
+                        continue;
+                    }
+                },
+            };
+
+            let mut outgoing = outgoing.into_iter().collect::<Vec<_>>();
+            outgoing.append(&mut self.pending);
+            for message in outgoing {
+                if socket.send(Message::Text(message.into())).await.is_err() {
+                    break 'connection;
+                }
+            }
+        }
+
+        log::debug!(
+            "Router client '{}' closed its WebSocket connection",
+            self.router_client.ip_address
+        );
+    }
+
+    fn notification(id: Option<NetworkEventId>, event: &NetworkEvent) -> Option<String> {
+        serde_json::to_string(&RpcNotification {
+            jsonrpc: "2.0",
+            method: "event",
+            params: EventParams { id, event },
+        })
+        .ok()
+    }
+
+    async fn handle_message(&mut self, text: &str) -> Option<String> {
+        let request = match serde_json::from_str::<Value>(text) {
+            Ok(request) => request,
+            Err(e) => {
+                return Self::response(
+                    Value::Null,
+                    Err(RpcError {
+                        code: PARSE_ERROR,
+                        message: e.to_string(),
+                    }),
+                );
+            }
+        };
+
+        let request = match serde_json::from_value::<RpcRequest>(request) {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            _ => {
+                return Self::response(
+                    Value::Null,
+                    Err(RpcError {
+                        code: INVALID_REQUEST,
+                        message: "Invalid JSON-RPC 2.0 request".to_owned(),
+                    }),
+                );
+            }
+        };
+
+        if !SESSION_METHODS.contains(&request.method.as_str()) {
+            if let Err(e) = self.user_session() {
+                return Self::response(request.id?, Err(e.into()));
+            }
+            self.spawn_call(request);
+            return None;
+        }
+
+        let result = self.call(&request.method, request.params).await;
+        let id = request.id?;
+        Self::response(id, result)
+    }
+
+    fn spawn_call(&self, request: RpcRequest) {
+        let netlink_service = self.netlink_service.clone();
+        let client_limit_service = self.client_limit_service.clone();
+        let responses = self.responses.clone();
+        tokio::spawn(async move {
+            let result = Self::call_api(
+                &netlink_service,
+                &client_limit_service,
+                &request.method,
+                request.params,
+            )
+            .await;
+            if let Some(response) = request.id.and_then(|id| Self::response(id, result)) {
+                // Fails only once the connection is closed
+                let _ = responses.send(response).await;
+            }
+        });
+    }
+
+    fn response(id: Value, result: core::result::Result<Value, RpcError>) -> Option<String> {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+
+        serde_json::to_string(&RpcResponse {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        })
+        .ok()
+    }
+
+    async fn call(&mut self, method: &str, params: Value) -> core::result::Result<Value, RpcError> {
+        match method {
+            "authenticate" => {
+                let params = parse_params::<AuthenticateParams>(params)?;
+                let session_id = params
+                    .auth_token
+                    .as_str()
+                    .try_into()
+                    .map_err(|_| Error::Unauthenticated)?;
+                self.auth_service.validate_session(session_id)?;
+                self.start_session(session_id, params.last_event_id)?;
+                to_rpc_result(Ok(AuthenticateResult {
+                    status: "OK".to_owned(),
+                }))
+            }
+            "login" => {
+                let response = api::login::handle(
+                    &self.router_client,
+                    &self.auth_service,
+                    parse_params(params)?,
+                )
+                .await?;
+                let session_id = response
+                    .auth_token
+                    .as_str()
+                    .try_into()
+                    .map_err(|_| Error::Unexpected)?;
+                self.start_session(session_id, None)?;
+                to_rpc_result(Ok(response))
+            }
+            "logout" => {
+                let user_session = self.user_session()?;
+                let response =
+                    api::logout::handle(&self.router_client, &user_session, &self.auth_service)
+                        .await;
+                self.session_id = None;
+                self.events = None;
+                to_rpc_result(response)
+            }
+            "authStatus" => {
+                self.user_session()?;
+                to_rpc_result(api::auth_status::handle().await)
+            }
+            _ => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Method not found: {}", method),
+            }),
+        }
+    }
+
+    // Calls of the HTTP endpoints under /api/net, for an authenticated client
+    async fn call_api(
+        netlink_service: &Arc<NetlinkService>,
+        client_limit_service: &ClientLimitService,
+        method: &str,
+        params: Value,
+    ) -> core::result::Result<Value, RpcError> {
+        match method {
+            "interfaces" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                to_rpc_result(api::net::interfaces::handle(&netlink_service).await)
+            }
+            "ifstate" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::ifstate::handle(&netlink_service, params).await)
+            }
+            "ifmode" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::ifmode::handle(&netlink_service, params).await)
+            }
+            "ifname" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::ifname::handle(&netlink_service, params).await)
+            }
+            "ifcreate" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::ifcreate::handle(&netlink_service, params).await)
+            }
+            "ifdelete" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::ifdelete::handle(&netlink_service, params).await)
+            }
+            "ifmaster" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::ifmaster::handle(&netlink_service, params).await)
+            }
+            "brconfig" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::brconfig::handle(&netlink_service, params).await)
+            }
+            "traffic" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::traffic::handle(&netlink_service, params).await)
+            }
+            "netns" => to_rpc_result(api::net::netns::handle(netlink_service).await),
+            "ifnetns" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::ifnetns::handle(&netlink_service, params).await)
+            }
+            "ifdetail" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::ifdetail::handle(&netlink_service, params).await)
+            }
+            "ethtool" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::ethtool::handle(&netlink_service, params).await)
+            }
+            "phys" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                to_rpc_result(api::net::phys::handle(&netlink_service).await)
+            }
+            "qdiscs" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::qdiscs::handle(&netlink_service, params).await)
+            }
+            "bwlimit" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::bwlimit::handle(&netlink_service, params).await)
+            }
+            "scan" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::scan::handle(&netlink_service, params).await)
+            }
+            "stations" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::stations::handle(&netlink_service, params).await)
+            }
+            "deauth" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::deauth::handle(&netlink_service, params).await)
+            }
+            "channel" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::channel::handle(&netlink_service, params).await)
+            }
+            "txpower" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::txpower::handle(&netlink_service, params).await)
+            }
+            "autochannel" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::autochannel::handle(&netlink_service, params).await)
+            }
+            "regdomain" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                to_rpc_result(api::net::regdomain::handle(&netlink_service).await)
+            }
+            "regcountry" => {
+                let netlink_service = Self::namespace(netlink_service, &params).await?;
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::regcountry::handle(&netlink_service, params).await)
+            }
+            "clientlimit" => {
+                let params = parse_params(params)?;
+                to_rpc_result(api::net::clientlimit::handle(client_limit_service, params).await)
+            }
+            "clientlimits" => {
+                to_rpc_result(api::net::clientlimits::handle(client_limit_service).await)
+            }
+            _ => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Method not found: {}", method),
+            }),
+        }
+    }
+
+    fn start_session(
+        &mut self,
+        session_id: SessionId,
+        last_event_id: Option<NetworkEventId>,
+    ) -> Result<()> {
+        let subscription = self
+            .netlink_service
+            .subscribe_network_events(last_event_id)
+            .map_err(|_| Error::Unexpected)?;
+
+        // Missed events are sent right after the response, before the live ones
+        if subscription.truncated {
+            self.pending
+                .extend(Self::notification(None, &NetworkEvent::ResyncRequired));
+        }
+        for (id, event) in subscription.backlog.iter() {
+            self.pending.extend(Self::notification(Some(*id), event));
+        }
+
+        self.session_id = Some(session_id);
+        self.events = Some(subscription.receiver);
+        Ok(())
+    }
+
+    // Interface methods take the same optional `netns` as the HTTP endpoints,
+    // as part of their parameters
+    async fn namespace(
+        netlink_service: &Arc<NetlinkService>,
+        params: &Value,
+    ) -> Result<Arc<NetlinkService>> {
+        let netns = params.get("netns").and_then(Value::as_str);
+        api::net::resolve_namespace(netlink_service, netns).await
+    }
+
+    // Sessions can expire or be replaced while the socket is open, so they
+    // are validated on every call
+    fn user_session(&mut self) -> Result<UserSession> {
+        let session_id = self.session_id.ok_or(Error::Unauthenticated)?;
+        if let Err(e) = self.auth_service.validate_session(session_id) {
+            self.session_id = None;
+            self.events = None;
+            return Err(e);
+        }
+
+        Ok(UserSession { session_id })
+    }
+}
+
+pub async fn get(
+    ws: WebSocketUpgrade,
+    router_client: RouterClient,
+    Extension(auth_service): Extension<Arc<AuthService>>,
+    Extension(netlink_service): Extension<Arc<NetlinkService>>,
+    Extension(client_limit_service): Extension<Arc<ClientLimitService>>,
+) -> Result<impl IntoResponse> {
+    let (responses, finished) = mpsc::channel(RESPONSE_CAPACITY);
+    let connection = RpcConnection {
+        router_client,
+        auth_service,
+        netlink_service,
+        client_limit_service,
+        session_id: None,
+        events: None,
+        pending: vec![],
+        responses,
+    };
+
+    Ok(ws.on_upgrade(move |socket| connection.run(socket, finished)))
+}
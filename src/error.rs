@@ -2,20 +2,52 @@ use std::collections::HashMap;
 
 use axum::{Json, http::StatusCode, response::IntoResponse};
 
+use crate::service::{NetlinkTimeout, TrafficControlConflict, WirelessRefusal};
+
 pub enum Error {
-    UnexpectedError,
+    Unexpected,
     RouterClientIdentificationFailed,
     SessionCooldown,
     IncorrectPassword,
     Unauthenticated,
     SessionExpired,
     InterfaceNotFound,
+    TrafficUnavailable,
+    InterfaceAlreadyExists,
+    InterfaceCreationFailed,
+    InterfaceNotVirtual,
+    InterfaceNotBridge,
+    StationRequires4Addr,
+    InterfaceRenameFailed,
+    NamespaceNotFound,
+    InterfaceMoveFailed,
+    NetlinkTimeout,
+    FeatureNotChangeable,
+    EthtoolSettingsFailed,
+    InvalidBandwidthLimit,
+    TrafficShapingFailed,
+    InvalidMacAddress,
+    ClientLimitFailed,
+    TrafficControlInUse,
+    InterfaceModeNotSupported,
+    InterfaceCombinationNotSupported,
+    LastWirelessInterface,
+    InterfaceNotWireless,
+    ScanFailed,
+    InterfaceNotAccessPoint,
+    StationNotFound,
+    InvalidChannel,
+    ChannelNotSupported,
+    InterfaceOperating,
+    WirelessSettingsFailed,
+    InvalidCountryCode,
+    RegulatoryChangeFailed,
 }
 
 impl Error {
     pub fn status_code(&self) -> StatusCode {
         match self {
-            Self::UnexpectedError | Self::RouterClientIdentificationFailed => {
+            Self::Unexpected | Self::RouterClientIdentificationFailed => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
             Self::SessionCooldown => StatusCode::TOO_MANY_REQUESTS,
@@ -23,18 +55,126 @@ impl Error {
             Self::Unauthenticated => StatusCode::UNAUTHORIZED,
             Self::SessionExpired => StatusCode::UNAUTHORIZED,
             Self::InterfaceNotFound => StatusCode::BAD_REQUEST,
+            Self::TrafficUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::InterfaceAlreadyExists => StatusCode::CONFLICT,
+            Self::InterfaceCreationFailed => StatusCode::BAD_REQUEST,
+            Self::InterfaceNotVirtual => StatusCode::BAD_REQUEST,
+            Self::InterfaceNotBridge => StatusCode::BAD_REQUEST,
+            Self::StationRequires4Addr => StatusCode::BAD_REQUEST,
+            Self::InterfaceRenameFailed => StatusCode::BAD_REQUEST,
+            Self::NamespaceNotFound => StatusCode::BAD_REQUEST,
+            Self::InterfaceMoveFailed => StatusCode::BAD_REQUEST,
+            Self::NetlinkTimeout => StatusCode::GATEWAY_TIMEOUT,
+            Self::FeatureNotChangeable => StatusCode::BAD_REQUEST,
+            Self::EthtoolSettingsFailed => StatusCode::BAD_REQUEST,
+            Self::InvalidBandwidthLimit => StatusCode::BAD_REQUEST,
+            Self::TrafficShapingFailed => StatusCode::BAD_REQUEST,
+            Self::InvalidMacAddress => StatusCode::BAD_REQUEST,
+            Self::ClientLimitFailed => StatusCode::BAD_REQUEST,
+            Self::TrafficControlInUse => StatusCode::CONFLICT,
+            Self::InterfaceModeNotSupported => StatusCode::BAD_REQUEST,
+            Self::InterfaceCombinationNotSupported => StatusCode::CONFLICT,
+            Self::LastWirelessInterface => StatusCode::CONFLICT,
+            Self::InterfaceNotWireless => StatusCode::BAD_REQUEST,
+            Self::ScanFailed => StatusCode::BAD_REQUEST,
+            Self::InterfaceNotAccessPoint => StatusCode::BAD_REQUEST,
+            Self::StationNotFound => StatusCode::BAD_REQUEST,
+            Self::InvalidChannel => StatusCode::BAD_REQUEST,
+            Self::ChannelNotSupported => StatusCode::BAD_REQUEST,
+            Self::InterfaceOperating => StatusCode::CONFLICT,
+            Self::WirelessSettingsFailed => StatusCode::BAD_REQUEST,
+            Self::InvalidCountryCode => StatusCode::BAD_REQUEST,
+            Self::RegulatoryChangeFailed => StatusCode::BAD_REQUEST,
         }
     }
 
     pub fn message(&self) -> &'static str {
         match self {
-            Self::UnexpectedError => "Unexpected error happened",
+            Self::Unexpected => "Unexpected error happened",
             Self::RouterClientIdentificationFailed => "Failed to identify the router client",
             Self::SessionCooldown => "Session creation is on cooldown",
             Self::IncorrectPassword => "Incorrect credentials",
             Self::Unauthenticated => "User is not authenticated",
             Self::SessionExpired => "Session has expired",
             Self::InterfaceNotFound => "The specified interface was not found",
+            Self::TrafficUnavailable => "Traffic statistics are not available for this interface",
+            Self::InterfaceAlreadyExists => "An interface with the specified name already exists",
+            Self::InterfaceCreationFailed => "Failed to create the specified interface",
+            Self::InterfaceNotVirtual => "The specified interface is not a virtual interface",
+            Self::InterfaceNotBridge => "The specified interface is not a bridge",
+            Self::StationRequires4Addr => {
+                "Station mode interfaces can only be bridged with 4-address (WDS) mode enabled"
+            }
+            Self::InterfaceRenameFailed => "Failed to rename the specified interface",
+            Self::NamespaceNotFound => "The specified network namespace was not found",
+            Self::InterfaceMoveFailed => {
+                "Failed to move the specified interface to the network namespace"
+            }
+            Self::NetlinkTimeout => "The network operation did not complete in time",
+            Self::FeatureNotChangeable => {
+                "The specified feature cannot be changed on this interface"
+            }
+            Self::EthtoolSettingsFailed => {
+                "Failed to apply the link settings to the specified interface"
+            }
+            Self::InvalidBandwidthLimit => "The bandwidth limit must be greater than zero",
+            Self::TrafficShapingFailed => {
+                "Failed to set up traffic shaping on the specified interface"
+            }
+            Self::InvalidMacAddress => "The specified MAC address is invalid",
+            Self::ClientLimitFailed => "Failed to apply the bandwidth limits of the client",
+            Self::TrafficControlInUse => {
+                "Traffic of the interface is already shaped by client limits or another setup"
+            }
+            Self::InterfaceModeNotSupported => {
+                "The wireless device does not support the requested interface mode"
+            }
+            Self::InterfaceCombinationNotSupported => {
+                "The wireless device cannot run this interface alongside its other interfaces"
+            }
+            Self::LastWirelessInterface => {
+                "The last interface of a wireless device cannot be deleted"
+            }
+            Self::InterfaceNotWireless => "The specified interface is not a wireless interface",
+            Self::ScanFailed => "Failed to scan for wireless networks",
+            Self::InterfaceNotAccessPoint => {
+                "The specified interface is not a wireless access point"
+            }
+            Self::StationNotFound => "The specified station is not connected to the interface",
+            Self::InvalidChannel => "Either a frequency or a channel number must be specified",
+            Self::ChannelNotSupported => {
+                "The wireless device cannot use the specified channel in its regulatory domain"
+            }
+            Self::InterfaceOperating => {
+                "The channel of an operating interface can only be switched by the program running it"
+            }
+            Self::WirelessSettingsFailed => {
+                "Failed to apply the wireless settings to the specified interface"
+            }
+            Self::InvalidCountryCode => "The specified country code is invalid",
+            Self::RegulatoryChangeFailed => "Failed to change the regulatory domain",
+        }
+    }
+
+    // Netlink operations that hit their deadline, links whose queueing is
+    // set up for something else, and wireless devices that turn down an
+    // interface are reported as such. Any other failure is reported as
+    // `fallback`.
+    pub fn from_netlink(error: &anyhow::Error, fallback: Self) -> Self {
+        if error.is::<NetlinkTimeout>() {
+            return Self::NetlinkTimeout;
+        }
+        if error.is::<TrafficControlConflict>() {
+            return Self::TrafficControlInUse;
+        }
+
+        match error.downcast_ref::<WirelessRefusal>() {
+            Some(WirelessRefusal::UnsupportedMode) => Self::InterfaceModeNotSupported,
+            Some(WirelessRefusal::UnsupportedCombination) => Self::InterfaceCombinationNotSupported,
+            Some(WirelessRefusal::UnsupportedChannel) => Self::ChannelNotSupported,
+            Some(WirelessRefusal::LastInterface) => Self::LastWirelessInterface,
+            Some(WirelessRefusal::InterfaceOperating) => Self::InterfaceOperating,
+            None => fallback,
         }
     }
 }
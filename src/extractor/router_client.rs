@@ -1,6 +1,5 @@
 use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr},
-    str::FromStr,
+    net::{IpAddr, SocketAddr},
     sync::Arc,
 };
 
@@ -8,11 +7,7 @@ use axum::{
     extract::{ConnectInfo, FromRequestParts},
     http::request::Parts,
 };
-use futures_util::stream::TryStreamExt;
 use macaddr::{MacAddr, MacAddr6};
-use rtnetlink::packet_route::link::LinkAttribute;
-use rtnetlink::packet_route::neighbour::NeighbourAddress;
-use rtnetlink::{Handle, packet_route::neighbour::NeighbourAttribute};
 
 use crate::{error::Error, service::NetlinkService};
 
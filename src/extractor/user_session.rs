@@ -1,23 +1,14 @@
-use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr},
-    str::FromStr,
-    sync::Arc,
-};
+use std::sync::Arc;
 
 use crate::{
     error::Error,
-    service::{AuthService, NetlinkService, SessionId},
-};
-use axum::{
-    RequestPartsExt,
-    extract::{ConnectInfo, FromRequestParts},
-    http::request::Parts,
+    service::{AuthService, SessionId},
 };
+use axum::{RequestPartsExt, extract::FromRequestParts, http::request::Parts};
 use axum_extra::{
     TypedHeader,
     headers::{Authorization, authorization::Bearer},
 };
-use uuid::Uuid;
 
 pub struct UserSession {
     pub session_id: SessionId,
@@ -51,7 +42,7 @@ where
 
         let auth_service = parts.extensions.get::<Arc<AuthService>>().ok_or_else(|| {
             log::error!("Failed to acquire AuthService");
-            Error::UnexpectedError
+            Error::Unexpected
         })?;
 
         log::trace!("Validating user session...");
@@ -3,17 +3,18 @@ mod error;
 mod extractor;
 mod service;
 
-use futures_util::stream::TryStreamExt;
-use rtnetlink::packet_route::link::LinkAttribute;
 use std::{net::SocketAddr, sync::Arc};
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::EnvFilter;
 
 use argon2::password_hash::PasswordHashString;
-use axum::{Extension, Router, routing::post};
+use axum::{
+    Extension, Router,
+    routing::{get, post},
+};
 use chrono::Duration;
 
-use crate::service::{AuthService, NetlinkService};
+use crate::service::{AuthService, ClientLimitService, NetlinkService, NetlinkTimeouts};
 
 pub struct AppState {}
 
@@ -36,7 +37,21 @@ async fn main() {
     .expect("failed to parse argon2id hash");
 
     tracing::info!("Initializing services...");
-    let netlink_service = NetlinkService::try_new().expect("failed to initialize netlink service");
+    let netlink_service = Arc::new(
+        NetlinkService::try_new(
+            Duration::seconds(1),
+            Duration::minutes(1),
+            NetlinkTimeouts::default(),
+        )
+        .await
+        .expect("failed to initialize netlink service"),
+    );
+    let client_limit_service = ClientLimitService::try_new(
+        netlink_service.clone(),
+        "client_limits.json",
+        Duration::seconds(5),
+    )
+    .expect("failed to initialize client limit service");
     let auth_service = AuthService::new(
         admin_password_hash,
         Duration::minutes(15),
@@ -47,16 +62,43 @@ async fn main() {
     let net = Router::new()
         .route("/interfaces", post(api::net::interfaces::post))
         .route("/ifstate", post(api::net::ifstate::post))
-        .route("/ifmode", post(api::net::ifmode::post));
+        .route("/ifmode", post(api::net::ifmode::post))
+        .route("/ifname", post(api::net::ifname::post))
+        .route("/ifcreate", post(api::net::ifcreate::post))
+        .route("/ifdelete", post(api::net::ifdelete::post))
+        .route("/ifmaster", post(api::net::ifmaster::post))
+        .route("/brconfig", post(api::net::brconfig::post))
+        .route("/traffic", post(api::net::traffic::post))
+        .route("/netns", post(api::net::netns::post))
+        .route("/ifnetns", post(api::net::ifnetns::post))
+        .route("/ifdetail", post(api::net::ifdetail::post))
+        .route("/ethtool", post(api::net::ethtool::post))
+        .route("/phys", post(api::net::phys::post))
+        .route("/qdiscs", post(api::net::qdiscs::post))
+        .route("/bwlimit", post(api::net::bwlimit::post))
+        .route("/scan", post(api::net::scan::post))
+        .route("/stations", post(api::net::stations::post))
+        .route("/deauth", post(api::net::deauth::post))
+        .route("/channel", post(api::net::channel::post))
+        .route("/txpower", post(api::net::txpower::post))
+        .route("/autochannel", post(api::net::autochannel::post))
+        .route("/regdomain", post(api::net::regdomain::post))
+        .route("/regcountry", post(api::net::regcountry::post))
+        .route("/clientlimit", post(api::net::clientlimit::post))
+        .route("/clientlimits", post(api::net::clientlimits::post));
     let api = Router::new()
         .route("/login", post(api::login::post))
         .route("/logout", post(api::logout::post))
         .route("/auth_status", post(api::auth_status::post))
+        .route("/events", get(api::events::get))
+        .route("/health", get(api::health::get))
+        .route("/ws", get(api::ws::get))
         .nest("/net", net);
     let app = Router::new()
         .nest("/api", api)
         .layer(Extension(Arc::new(auth_service)))
-        .layer(Extension(Arc::new(netlink_service)));
+        .layer(Extension(Arc::new(client_limit_service)))
+        .layer(Extension(netlink_service));
     let hostaddr = "127.0.0.1:8080";
     let listener = tokio::net::TcpListener::bind(hostaddr)
         .await
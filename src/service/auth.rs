@@ -47,7 +47,7 @@ impl AuthService {
     pub fn validate_session(&self, session_id: SessionId) -> Result<(), Error> {
         let mut global_session_lock = Self::global_session().write().map_err(|_| {
             log::error!("Failed to acquire write lock for global session");
-            Error::UnexpectedError
+            Error::Unexpected
         })?;
 
         let Some(global_session) = global_session_lock.as_ref() else {
@@ -75,7 +75,7 @@ impl AuthService {
 
         let mut global_session = Self::global_session().write().map_err(|_| {
             log::error!("Failed to acquire write lock for global session");
-            Error::UnexpectedError
+            Error::Unexpected
         })?;
 
         if global_session
@@ -102,7 +102,7 @@ impl AuthService {
     pub fn sign_out(&self) -> Result<(), Error> {
         let mut global_session_lock = Self::global_session().write().map_err(|_| {
             log::error!("Failed to acquire write lock for global session");
-            Error::UnexpectedError
+            Error::Unexpected
         })?;
 
         *global_session_lock = None;
@@ -0,0 +1,292 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    net::IpAddr,
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use chrono::Duration;
+use macaddr::MacAddr;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::Mutex, task::JoinHandle, time::MissedTickBehavior};
+
+use crate::service::{AddressLimit, NetlinkService, TrafficControlConflict};
+
+// Caps of a client device in kbit/s, where upload is the traffic sent by the
+// client. A missing cap leaves that direction unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientLimit {
+    pub upload: Option<u64>,
+    pub download: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredClientLimit {
+    mac_address: String,
+    #[serde(flatten)]
+    limit: ClientLimit,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientLimitStatus {
+    pub mac_address: String,
+    #[serde(flatten)]
+    pub limit: ClientLimit,
+    // Addresses the client currently has in the neighbour table, which the
+    // limits are applied to
+    pub ip_addresses: Vec<IpAddr>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct InterfaceLimits {
+    download: Vec<AddressLimit>,
+    upload: Vec<AddressLimit>,
+}
+
+impl InterfaceLimits {
+    fn is_empty(&self) -> bool {
+        self.download.is_empty() && self.upload.is_empty()
+    }
+}
+
+struct ClientLimitState {
+    netlink_service: Arc<NetlinkService>,
+    path: PathBuf,
+    limits: Mutex<BTreeMap<MacAddr, ClientLimit>>,
+    // Limits last applied to each interface, by index
+    applied: Mutex<HashMap<u32, InterfaceLimits>>,
+    // Limits that can't be applied because the queueing of their interface
+    // is set up for something else, by index
+    blocked: Mutex<HashMap<u32, InterfaceLimits>>,
+}
+
+// Keeps per-client limits, keyed by MAC address, applied to whatever IP
+// addresses the clients have in the neighbour table
+pub struct ClientLimitService {
+    state: Arc<ClientLimitState>,
+    reconcile_future: JoinHandle<()>,
+}
+
+impl ClientLimitService {
+    pub fn try_new(
+        netlink_service: Arc<NetlinkService>,
+        path: impl Into<PathBuf>,
+        reconcile_interval: Duration,
+    ) -> Result<Self> {
+        let path = path.into();
+        let limits = Self::load(&path)?;
+        let state = Arc::new(ClientLimitState {
+            netlink_service,
+            path,
+            limits: Mutex::new(limits),
+            applied: Mutex::new(HashMap::new()),
+            blocked: Mutex::new(HashMap::new()),
+        });
+        let reconcile_future = tokio::spawn(Self::run(state.clone(), reconcile_interval.to_std()?));
+
+        Ok(Self {
+            state,
+            reconcile_future,
+        })
+    }
+
+    fn load(path: &PathBuf) -> Result<BTreeMap<MacAddr, ClientLimit>> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e).context("failed to read client limits"),
+        };
+
+        serde_json::from_slice::<Vec<StoredClientLimit>>(&data)
+            .context("failed to parse client limits")?
+            .into_iter()
+            .map(|x| Ok((MacAddr::from_str(&x.mac_address)?, x.limit)))
+            .collect()
+    }
+
+    // Written to a temporary file first, so that a crash never leaves a
+    // truncated store behind
+    fn save(path: &PathBuf, limits: &BTreeMap<MacAddr, ClientLimit>) -> Result<()> {
+        let stored = limits
+            .iter()
+            .map(|(mac_address, limit)| StoredClientLimit {
+                mac_address: mac_address.to_string(),
+                limit: *limit,
+            })
+            .collect::<Vec<_>>();
+
+        let mut temp_path = path.clone().into_os_string();
+        temp_path.push(".tmp");
+        fs::write(&temp_path, serde_json::to_vec_pretty(&stored)?)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    async fn run(state: Arc<ClientLimitState>, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            if let Err(e) = Self::reconcile(&state).await {
+                log::error!("Failed to apply client limits: {}", e);
+            }
+        }
+    }
+
+    // Brings the limits in place in line with the stored ones and the current
+    // addresses of the clients. Interfaces whose limits haven't changed are
+    // left alone, unless something else replaced them.
+    async fn reconcile(state: &ClientLimitState) -> Result<()> {
+        let mut applied = state.applied.lock().await;
+        let mut blocked = state.blocked.lock().await;
+        let neighbours = state.netlink_service.get_neighbours().await?;
+
+        let mut desired = HashMap::<u32, InterfaceLimits>::new();
+        for (mac_address, limit) in state.limits.lock().await.iter() {
+            let mut addresses = BTreeMap::<u32, Vec<IpAddr>>::new();
+            for neighbour in neighbours.iter().filter(|x| x.mac_address == *mac_address) {
+                addresses
+                    .entry(neighbour.index)
+                    .or_default()
+                    .push(neighbour.ip_address);
+            }
+
+            for (index, mut addresses) in addresses {
+                addresses.sort();
+                let limits = desired.entry(index).or_default();
+                if let Some(rate) = limit.download {
+                    limits.download.push(AddressLimit {
+                        addresses: addresses.clone(),
+                        rate,
+                    });
+                }
+                if let Some(rate) = limit.upload {
+                    limits.upload.push(AddressLimit { addresses, rate });
+                }
+            }
+        }
+
+        let mut indexes = desired
+            .keys()
+            .chain(applied.keys())
+            .copied()
+            .collect::<Vec<_>>();
+        indexes.sort();
+        indexes.dedup();
+
+        let mut result = Ok(());
+        for index in indexes {
+            let wanted = desired.remove(&index).unwrap_or_default();
+            let current = applied.get(&index);
+
+            let unchanged = match current {
+                Some(current) if *current == wanted => {
+                    wanted.is_empty()
+                        || state
+                            .netlink_service
+                            .count_address_limits(index)
+                            .await
+                            .is_ok_and(|x| x == (wanted.download.len(), wanted.upload.len()))
+                }
+                Some(_) => false,
+                None => wanted.is_empty(),
+            };
+            if unchanged {
+                continue;
+            }
+
+            log::info!("Applying client limits to interface {}", index);
+            match state
+                .netlink_service
+                .set_address_limits(index, wanted.download.clone(), wanted.upload.clone())
+                .await
+            {
+                Ok(()) if wanted.is_empty() => {
+                    applied.remove(&index);
+                    blocked.remove(&index);
+                }
+                Ok(()) => {
+                    blocked.remove(&index);
+                    applied.insert(index, wanted);
+                }
+                // Retried until the interface is free, but only reported
+                // once for the same limits
+                Err(e) if e.is::<TrafficControlConflict>() => {
+                    if blocked.insert(index, wanted.clone()).as_ref() != Some(&wanted) {
+                        result = Err(e.context(format!("interface {}", index)));
+                    }
+                }
+                Err(e) => {
+                    // Limits being removed usually fail because their
+                    // interface is gone, and took them along
+                    if wanted.is_empty() {
+                        applied.remove(&index);
+                    }
+                    result = Err(e.context(format!("interface {}", index)));
+                }
+            }
+        }
+
+        result
+    }
+
+    pub async fn get_limits(&self) -> Result<Vec<ClientLimitStatus>> {
+        let neighbours = self.state.netlink_service.get_neighbours().await?;
+
+        Ok(self
+            .state
+            .limits
+            .lock()
+            .await
+            .iter()
+            .map(|(mac_address, limit)| {
+                let mut ip_addresses = neighbours
+                    .iter()
+                    .filter(|x| x.mac_address == *mac_address)
+                    .map(|x| x.ip_address)
+                    .collect::<Vec<_>>();
+                ip_addresses.sort();
+                ClientLimitStatus {
+                    mac_address: mac_address.to_string(),
+                    limit: *limit,
+                    ip_addresses,
+                }
+            })
+            .collect())
+    }
+
+    // Stores the limit of the client, or removes it if it has no caps, and
+    // applies it right away
+    pub async fn set_limit(&self, mac_address: MacAddr, limit: ClientLimit) -> Result<()> {
+        {
+            let mut limits = self.state.limits.lock().await;
+            let previous = limits.clone();
+            if limit.upload.is_none() && limit.download.is_none() {
+                limits.remove(&mac_address);
+            } else {
+                limits.insert(mac_address, limit);
+            }
+
+            if let Err(e) = Self::save(&self.state.path, &limits) {
+                *limits = previous;
+                return Err(e.context("failed to save client limits"));
+            }
+        }
+
+        Self::reconcile(&self.state).await
+    }
+}
+
+impl Drop for ClientLimitService {
+    fn drop(&mut self) {
+        self.reconcile_future.abort();
+    }
+}
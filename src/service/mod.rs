@@ -1,4 +1,6 @@
 mod auth;
+mod client_limit;
 mod netlink;
 pub use auth::*;
+pub use client_limit::*;
 pub use netlink::*;
@@ -0,0 +1,106 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use macaddr::MacAddr;
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
+
+use crate::service::netlink::{
+    events::NetlinkEvent,
+    timeout::with_deadline,
+    wiphy::{DEAUTH_REASON_CODE, WiphyManager},
+};
+
+type Blocks = Mutex<HashMap<(u32, MacAddr), DateTime<Utc>>>;
+
+// Keeps stations from reconnecting to an access point for a while. mac80211
+// leaves authentication to userspace, so blocked stations are deauthenticated
+// again as soon as they show up.
+pub struct StationBlocker {
+    // Ends of the blocks, by interface index and station
+    blocks: Arc<Blocks>,
+    block_future: JoinHandle<()>,
+}
+
+impl StationBlocker {
+    pub fn new(
+        wiphy_mgr: Arc<WiphyManager>,
+        events: broadcast::Receiver<NetlinkEvent>,
+        timeout: Duration,
+    ) -> Self {
+        let blocks = Arc::new(Mutex::new(HashMap::new()));
+        let block_future = tokio::spawn(Self::run(wiphy_mgr, blocks.clone(), events, timeout));
+
+        Self {
+            blocks,
+            block_future,
+        }
+    }
+
+    pub fn block(&self, index: u32, mac_address: MacAddr, duration: Duration) {
+        let mut blocks = self.blocks.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Utc::now();
+        blocks.retain(|_, until| *until > now);
+        blocks.insert((index, mac_address), now + duration);
+    }
+
+    pub fn unblock(&self, index: u32, mac_address: MacAddr) {
+        let mut blocks = self.blocks.lock().unwrap_or_else(|e| e.into_inner());
+        blocks.remove(&(index, mac_address));
+    }
+
+    fn is_blocked(blocks: &Blocks, index: u32, mac_address: MacAddr) -> bool {
+        let blocks = blocks.lock().unwrap_or_else(|e| e.into_inner());
+        blocks
+            .get(&(index, mac_address))
+            .is_some_and(|until| *until > Utc::now())
+    }
+
+    async fn run(
+        wiphy_mgr: Arc<WiphyManager>,
+        blocks: Arc<Blocks>,
+        mut events: broadcast::Receiver<NetlinkEvent>,
+        timeout: Duration,
+    ) {
+        loop {
+            let (index, mac_address) = match events.recv().await {
+                Ok(NetlinkEvent::StationConnected { index, mac_address }) => (index, mac_address),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(count)) => {
+                    log::warn!("Station blocker missed {} events", count);
+                    continue;
+                }
+                Err(RecvError::Closed) => {
+                    log::warn!("Event channel closed, stations are no longer blocked");
+                    return;
+                }
+            };
+
+            if !Self::is_blocked(&blocks, index, mac_address) {
+                continue;
+            }
+
+            log::info!("Deauthenticating blocked station {}", mac_address);
+            let result = with_deadline(
+                "deauthenticate blocked station",
+                timeout,
+                wiphy_mgr.del_station(index, mac_address, DEAUTH_REASON_CODE),
+            )
+            .await;
+            if let Err(e) = result {
+                log::error!("Failed to deauthenticate blocked station: {}", e);
+            }
+        }
+    }
+}
+
+impl Drop for StationBlocker {
+    fn drop(&mut self) {
+        self.block_future.abort();
+    }
+}
@@ -0,0 +1,524 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{Result, anyhow};
+use chrono::Duration;
+use tokio::{
+    sync::{broadcast, broadcast::error::RecvError, mpsc, oneshot},
+    task::JoinHandle,
+    time::MissedTickBehavior,
+};
+
+use crate::service::netlink::{
+    events::NetlinkEvent,
+    interface::{NetlinkInterface, NetlinkInterfaceModeStatus},
+    journal::{EventJournal, NetworkEvent},
+    route::{RouteInterface, RouteInterfaceKind, RouteManager},
+    timeout::with_deadline,
+    wiphy::{WiphyDevice, WiphyInterface, WiphyManager},
+};
+
+// Resyncs requested while another one is running
+const RESYNC_REQUEST_CAPACITY: usize = 16;
+
+#[derive(Default)]
+struct CacheState {
+    interfaces: BTreeMap<u32, NetlinkInterface>,
+    // Wireless physical devices, by index
+    phys: HashMap<u32, WiphyDevice>,
+}
+
+impl CacheState {
+    fn mode_status(&self, iface: &WiphyInterface) -> Option<NetlinkInterfaceModeStatus> {
+        let phy = self.phys.get(&iface.phy_index)?;
+        Some(NetlinkInterfaceModeStatus {
+            active: iface.iftype.into(),
+            supported: phy.supported_modes(),
+            software: phy.software_modes(),
+            combinations: phy.interface_combinations.clone(),
+            use_4addr: iface.use_4addr,
+        })
+    }
+
+    fn update_route_interface(&mut self, iface: RouteInterface) {
+        let interface = self
+            .interfaces
+            .entry(iface.index)
+            .or_insert_with(|| NetlinkInterface {
+                index: iface.index,
+                name: iface.name.clone(),
+                kind: iface.kind.clone(),
+                link_flags: None,
+                mode_status: None,
+                phy_index: None,
+                phy: None,
+                channel: None,
+                tx_power: None,
+                stats: None,
+                controller_index: None,
+                controller: None,
+                bridge_options: None,
+            });
+
+        // Route data is authoritative for everything but the wireless mode
+        interface.name = iface.name;
+        interface.kind = match interface.mode_status {
+            Some(_) => RouteInterfaceKind::Wireless,
+            None => iface.kind,
+        };
+        interface.link_flags = Some(iface.link_flags);
+        interface.stats = iface.stats;
+        interface.controller_index = iface.controller_index;
+        interface.bridge_options = iface.bridge_options;
+    }
+
+    // Returns false if the interface belongs to an unknown physical device
+    fn update_wiphy_interface(&mut self, iface: WiphyInterface) -> bool {
+        let Some(mode_status) = self.mode_status(&iface) else {
+            log::debug!(
+                "Wireless physical device '{}' of interface '{}' is not cached",
+                iface.phy_index,
+                iface.name
+            );
+            return false;
+        };
+
+        let interface = self
+            .interfaces
+            .entry(iface.index)
+            .or_insert_with(|| NetlinkInterface {
+                index: iface.index,
+                name: iface.name,
+                kind: RouteInterfaceKind::Wireless,
+                link_flags: None,
+                mode_status: None,
+                phy_index: None,
+                phy: None,
+                channel: None,
+                tx_power: None,
+                stats: None,
+                controller_index: None,
+                controller: None,
+                bridge_options: None,
+            });
+        interface.kind = RouteInterfaceKind::Wireless;
+        interface.mode_status = Some(mode_status);
+        interface.phy_index = Some(iface.phy_index);
+        interface.phy = self.phys.get(&iface.phy_index).map(|x| x.phy_name.clone());
+        interface.channel = iface.channel;
+        interface.tx_power = iface.tx_power;
+        true
+    }
+
+    fn interface_name(&self, index: u32) -> String {
+        self.interfaces
+            .get(&index)
+            .map(|x| x.name.clone())
+            .unwrap_or_else(|| index.to_string())
+    }
+
+    // Resolve the names of the bridges and bonds that interfaces are attached to
+    fn resolve_controllers(&mut self) {
+        let names = self
+            .interfaces
+            .values()
+            .map(|x| (x.index, x.name.clone()))
+            .collect::<HashMap<_, _>>();
+        for iface in self.interfaces.values_mut() {
+            iface.controller = iface
+                .controller_index
+                .and_then(|index| names.get(&index).cloned());
+        }
+    }
+}
+
+struct CacheInner {
+    state: RwLock<CacheState>,
+    wiphy_mgr: Arc<WiphyManager>,
+    route_mgr: Arc<RouteManager>,
+    journal: Arc<EventJournal>,
+    query_timeout: Duration,
+}
+
+// Resyncs are only run by the cache task, between events, so that a dump
+// never overwrites events applied while it was made
+type ResyncRequest = oneshot::Sender<Result<()>>;
+
+// Keeps the current interfaces in memory, so that reads don't need to dump
+// every link and wireless interface. The cache is kept up to date by netlink
+// events and periodically resynchronized to recover from missed events.
+pub struct InterfaceCache {
+    inner: Arc<CacheInner>,
+    resync_requests: mpsc::Sender<ResyncRequest>,
+    cache_future: JoinHandle<()>,
+}
+
+impl InterfaceCache {
+    pub async fn try_new(
+        wiphy_mgr: Arc<WiphyManager>,
+        route_mgr: Arc<RouteManager>,
+        journal: Arc<EventJournal>,
+        events: broadcast::Receiver<NetlinkEvent>,
+        resync_interval: Duration,
+        query_timeout: Duration,
+    ) -> Result<Self> {
+        let inner = Arc::new(CacheInner {
+            state: RwLock::new(CacheState::default()),
+            wiphy_mgr,
+            route_mgr,
+            journal,
+            query_timeout,
+        });
+
+        // Events received during the initial sync are applied on top of it.
+        // The interfaces found by it are not reported as added.
+        Self::resync_inner(&inner).await?;
+        let (resync_requests, requests) = mpsc::channel(RESYNC_REQUEST_CAPACITY);
+        let cache_future = tokio::spawn(Self::run(
+            inner.clone(),
+            events,
+            requests,
+            resync_interval.to_std()?,
+        ));
+
+        Ok(Self {
+            inner,
+            resync_requests,
+            cache_future,
+        })
+    }
+
+    async fn run(
+        inner: Arc<CacheInner>,
+        mut events: broadcast::Receiver<NetlinkEvent>,
+        mut requests: mpsc::Receiver<ResyncRequest>,
+        resync_interval: std::time::Duration,
+    ) {
+        let mut ticker = tokio::time::interval(resync_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately, and the cache was just synced
+        ticker.tick().await;
+        let mut events_closed = false;
+
+        loop {
+            let needs_resync = tokio::select! {
+                _ = ticker.tick() => true,
+                request = requests.recv() => match request {
+                    Some(reply) => {
+                        let _ = reply.send(Self::resync_and_record(&inner).await);
+                        ticker.reset();
+                        false
+                    }
+                    // The cache has been dropped
+                    None => return,
+                },
+                event = events.recv(), if !events_closed => match event {
+                    Ok(event) => !Self::apply(&inner, event),
+                    Err(RecvError::Lagged(count)) => {
+                        log::warn!("Interface cache missed {} events, resyncing...", count);
+                        true
+                    }
+                    Err(RecvError::Closed) => {
+                        log::warn!("Event channel closed, interface cache will only be resynced");
+                        events_closed = true;
+                        false
+                    }
+                },
+            };
+
+            if needs_resync && let Err(e) = Self::resync_and_record(&inner).await {
+                log::error!("Failed to resync interface cache: {}", e);
+            }
+        }
+    }
+
+    // Returns false if the event could not be applied and a resync is needed
+    fn apply(inner: &CacheInner, event: NetlinkEvent) -> bool {
+        let Ok(mut state) = inner.state.write() else {
+            log::error!("Failed to acquire write lock for interface cache");
+            return false;
+        };
+
+        let mut changes = vec![];
+        let applied = match event {
+            NetlinkEvent::LinkUpdated(iface) => {
+                let index = iface.index;
+                let before = state.interfaces.get(&index).cloned();
+                state.update_route_interface(iface);
+                changes = interface_changes(before.as_ref(), state.interfaces.get(&index));
+                true
+            }
+            NetlinkEvent::LinkRemoved(RouteInterface { index, .. })
+            | NetlinkEvent::WirelessInterfaceRemoved(WiphyInterface { index, .. }) => {
+                let before = state.interfaces.remove(&index);
+                changes = interface_changes(before.as_ref(), None);
+                true
+            }
+            NetlinkEvent::WirelessInterfaceUpdated(iface) => {
+                let index = iface.index;
+                let before = state.interfaces.get(&index).cloned();
+                let applied = state.update_wiphy_interface(iface);
+                changes = interface_changes(before.as_ref(), state.interfaces.get(&index));
+                applied
+            }
+            // Supported modes are only available from a full device dump
+            NetlinkEvent::WirelessDeviceChanged { phy_index } => {
+                log::debug!("Wireless device {} changed, resyncing...", phy_index);
+                false
+            }
+            // Notifications may have been lost
+            NetlinkEvent::Overrun | NetlinkEvent::Reconnected => false,
+            NetlinkEvent::AddressAdded {
+                index,
+                address,
+                prefix_len,
+            } => {
+                changes.push(NetworkEvent::AddressAdded {
+                    interface_name: state.interface_name(index),
+                    address,
+                    prefix_len,
+                });
+                true
+            }
+            NetlinkEvent::AddressRemoved {
+                index,
+                address,
+                prefix_len,
+            } => {
+                changes.push(NetworkEvent::AddressRemoved {
+                    interface_name: state.interface_name(index),
+                    address,
+                    prefix_len,
+                });
+                true
+            }
+            NetlinkEvent::NeighbourUpdated {
+                index,
+                ip_address,
+                mac_address,
+            } => {
+                changes.push(NetworkEvent::NeighbourUpdated {
+                    interface_name: state.interface_name(index),
+                    ip_address,
+                    mac_address: mac_address.map(|x| x.to_string()),
+                });
+                true
+            }
+            NetlinkEvent::NeighbourRemoved {
+                index,
+                ip_address,
+                mac_address,
+            } => {
+                changes.push(NetworkEvent::NeighbourRemoved {
+                    interface_name: state.interface_name(index),
+                    ip_address,
+                    mac_address: mac_address.map(|x| x.to_string()),
+                });
+                true
+            }
+            NetlinkEvent::RouteAdded {
+                destination,
+                prefix_len,
+                gateway,
+                output_index,
+            } => {
+                changes.push(NetworkEvent::RouteAdded {
+                    destination,
+                    prefix_len,
+                    gateway,
+                    interface_name: output_index.map(|x| state.interface_name(x)),
+                });
+                true
+            }
+            NetlinkEvent::RouteRemoved {
+                destination,
+                prefix_len,
+                gateway,
+                output_index,
+            } => {
+                changes.push(NetworkEvent::RouteRemoved {
+                    destination,
+                    prefix_len,
+                    gateway,
+                    interface_name: output_index.map(|x| state.interface_name(x)),
+                });
+                true
+            }
+            NetlinkEvent::StationConnected { index, mac_address } => {
+                changes.push(NetworkEvent::ClientConnected {
+                    interface_name: state.interface_name(index),
+                    mac_address: mac_address.to_string(),
+                });
+                true
+            }
+            NetlinkEvent::StationDisconnected { index, mac_address } => {
+                changes.push(NetworkEvent::ClientDisconnected {
+                    interface_name: state.interface_name(index),
+                    mac_address: mac_address.to_string(),
+                });
+                true
+            }
+            NetlinkEvent::RegulatoryChanged { country } => {
+                changes.push(NetworkEvent::RegulatoryDomainChanged { country });
+                true
+            }
+            _ => true,
+        };
+
+        state.resolve_controllers();
+        drop(state);
+
+        changes.into_iter().for_each(|x| inner.journal.record(x));
+        applied
+    }
+
+    // Returns once the cache task has resynced the cache
+    pub async fn resync(&self) -> Result<()> {
+        let (reply, response) = oneshot::channel();
+        self.resync_requests
+            .send(reply)
+            .await
+            .map_err(|_| anyhow!("Interface cache is not running"))?;
+        response
+            .await
+            .map_err(|_| anyhow!("Interface cache stopped before resyncing"))?
+    }
+
+    async fn resync_and_record(inner: &CacheInner) -> Result<()> {
+        let changes = Self::resync_inner(inner).await?;
+        changes.into_iter().for_each(|x| inner.journal.record(x));
+        Ok(())
+    }
+
+    // Returns the changes between the previous and the new interfaces
+    async fn resync_inner(inner: &CacheInner) -> Result<Vec<NetworkEvent>> {
+        let timeout = inner.query_timeout;
+        let phys = with_deadline(
+            "get wireless devices",
+            timeout,
+            inner.wiphy_mgr.get_wiphy_devices(),
+        )
+        .await?
+        .into_iter()
+        .map(|x| (x.phy_index, x))
+        .collect::<HashMap<_, _>>();
+        let mut state = CacheState {
+            interfaces: BTreeMap::new(),
+            phys,
+        };
+
+        let wiphy_interfaces = with_deadline(
+            "get wireless interfaces",
+            timeout,
+            inner.wiphy_mgr.get_wiphy_interfaces(),
+        )
+        .await?;
+        let route_interfaces =
+            with_deadline("get links", timeout, inner.route_mgr.get_interfaces()).await?;
+
+        // Wireless and route data are joined by index, which is stable across
+        // renames. Wireless interfaces that are missing from the route dump,
+        // e.g. because they were created in between both requests, are kept
+        // without route information.
+        for iface in wiphy_interfaces {
+            state.update_wiphy_interface(iface);
+        }
+        for iface in route_interfaces {
+            state.update_route_interface(iface);
+        }
+        state.resolve_controllers();
+
+        let mut previous = inner
+            .state
+            .write()
+            .map_err(|_| anyhow!("Failed to acquire write lock for interface cache"))?;
+        let mut indices = previous.interfaces.keys().copied().collect::<Vec<_>>();
+        indices.extend(state.interfaces.keys());
+        indices.sort_unstable();
+        indices.dedup();
+        let changes = indices
+            .into_iter()
+            .flat_map(|index| {
+                interface_changes(
+                    previous.interfaces.get(&index),
+                    state.interfaces.get(&index),
+                )
+            })
+            .collect();
+
+        *previous = state;
+        Ok(changes)
+    }
+
+    pub fn get_interfaces(&self) -> Result<Vec<NetlinkInterface>> {
+        let state = self
+            .inner
+            .state
+            .read()
+            .map_err(|_| anyhow!("Failed to acquire read lock for interface cache"))?;
+        Ok(state.interfaces.values().cloned().collect())
+    }
+
+    pub fn find_interface_by_name(&self, name: &str) -> Result<NetlinkInterface> {
+        let state = self
+            .inner
+            .state
+            .read()
+            .map_err(|_| anyhow!("Failed to acquire read lock for interface cache"))?;
+        state
+            .interfaces
+            .values()
+            .find(|x| x.name == name)
+            .cloned()
+            .ok_or(anyhow!("Could not find interface with name: {}", name))
+    }
+}
+
+fn interface_changes(
+    before: Option<&NetlinkInterface>,
+    after: Option<&NetlinkInterface>,
+) -> Vec<NetworkEvent> {
+    let (before, after) = match (before, after) {
+        (None, None) => return vec![],
+        (None, Some(after)) => {
+            return vec![NetworkEvent::InterfaceAdded {
+                interface: Box::new(after.clone()),
+            }];
+        }
+        (Some(before), None) => {
+            return vec![NetworkEvent::InterfaceRemoved {
+                interface_name: before.name.clone(),
+            }];
+        }
+        (Some(before), Some(after)) => (before, after),
+    };
+
+    let mut changes = vec![];
+    if let Some(link_state) = after.state()
+        && before.state() != Some(link_state)
+    {
+        changes.push(NetworkEvent::LinkStateChanged {
+            interface_name: after.name.clone(),
+            link_state,
+        });
+    }
+
+    if let Some(mode_status) = &after.mode_status
+        && before.mode_status.as_ref().map(|x| &x.active) != Some(&mode_status.active)
+    {
+        changes.push(NetworkEvent::ModeChanged {
+            interface_name: after.name.clone(),
+            mode: mode_status.active.clone(),
+        });
+    }
+
+    changes
+}
+
+impl Drop for InterfaceCache {
+    fn drop(&mut self) {
+        self.cache_future.abort();
+    }
+}
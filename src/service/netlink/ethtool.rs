@@ -0,0 +1,523 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::CStr,
+    io, mem,
+    os::fd::AsRawFd,
+    sync::Arc,
+};
+
+use ::ethtool::{
+    EthtoolAttr, EthtoolError, EthtoolFeatureAttr, EthtoolHandle, EthtoolLinkModeAttr,
+    EthtoolLinkModeDuplex,
+};
+use anyhow::{Result, anyhow};
+use futures_util::{StreamExt, TryStreamExt, future};
+use netlink_packet_generic::{GenlFamily, GenlHeader, GenlMessage};
+use nix::{
+    libc,
+    sys::socket::{AddressFamily, SockFlag, SockType, socket},
+};
+use rtnetlink::packet_core::{
+    DecodeError, Emitable, NLA_F_NESTED, NLM_F_ACK, NLM_F_REQUEST, NetlinkMessage, NetlinkPayload,
+    Nla, ParseableParametrized,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::service::netlink::{
+    netns::enter_namespace,
+    supervisor::{Connection, ConnectionHealth, HealthMonitor, SupervisedHandle},
+};
+
+// Requests and attributes that the ethtool crate can't emit yet
+const ETHTOOL_MSG_LINKMODES_SET: u8 = 5;
+const ETHTOOL_MSG_FEATURES_SET: u8 = 12;
+const ETHTOOL_A_HEADER_DEV_NAME: u16 = 2;
+const ETHTOOL_A_LINKMODES_HEADER: u16 = 1;
+const ETHTOOL_A_LINKMODES_AUTONEG: u16 = 2;
+const ETHTOOL_A_LINKMODES_SPEED: u16 = 5;
+const ETHTOOL_A_LINKMODES_DUPLEX: u16 = 6;
+const ETHTOOL_A_FEATURES_HEADER: u16 = 1;
+const ETHTOOL_A_FEATURES_WANTED: u16 = 3;
+const ETHTOOL_A_BITSET_BITS: u16 = 3;
+const ETHTOOL_A_BITSET_BITS_BIT: u16 = 1;
+const ETHTOOL_A_BITSET_BIT_NAME: u16 = 2;
+const ETHTOOL_A_BITSET_BIT_VALUE: u16 = 3;
+const DUPLEX_HALF: u8 = 0;
+const DUPLEX_FULL: u8 = 1;
+
+// Reported by the kernel when the speed of the link is not known
+const SPEED_UNKNOWN: u32 = u32::MAX;
+
+// Driver information is not available through ethtool netlink, so it is
+// queried through the legacy ioctl
+const ETHTOOL_GDRVINFO: u32 = 0x3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EthtoolDuplex {
+    Half,
+    Full,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthtoolLinkModes {
+    // Speed in Mb/s, missing when the link is down or it is unknown
+    pub speed: Option<u32>,
+    pub duplex: Option<EthtoolDuplex>,
+    pub autoneg: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthtoolDriverInfo {
+    pub driver: String,
+    pub version: Option<String>,
+    pub firmware_version: Option<String>,
+    pub bus_info: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthtoolFeature {
+    pub name: String,
+    pub active: bool,
+    // Whether the feature can be toggled on this device
+    pub changeable: bool,
+}
+
+// Everything ethtool reports about an interface. Parts that the device
+// doesn't support are left out.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthtoolInfo {
+    pub link_modes: Option<EthtoolLinkModes>,
+    pub driver_info: Option<EthtoolDriverInfo>,
+    pub features: Vec<EthtoolFeature>,
+}
+
+// Settings to change on an interface. Only the ones that are set are
+// changed, features are toggled by name.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthtoolSettings {
+    pub speed: Option<u32>,
+    pub duplex: Option<EthtoolDuplex>,
+    pub autoneg: Option<bool>,
+    #[serde(default)]
+    pub features: BTreeMap<String, bool>,
+}
+
+impl EthtoolSettings {
+    fn has_link_modes(&self) -> bool {
+        self.speed.is_some() || self.duplex.is_some() || self.autoneg.is_some()
+    }
+}
+
+// Attributes of the requests sent to the ethtool family
+#[derive(Debug, Clone)]
+enum EthtoolRequestAttr {
+    Flag(u16),
+    U8(u16, u8),
+    U32(u16, u32),
+    String(u16, String),
+    Nested(u16, Vec<EthtoolRequestAttr>),
+}
+
+impl EthtoolRequestAttr {
+    fn header(kind: u16, interface_name: &str) -> Self {
+        Self::Nested(
+            kind,
+            vec![Self::String(
+                ETHTOOL_A_HEADER_DEV_NAME,
+                interface_name.to_owned(),
+            )],
+        )
+    }
+}
+
+impl Nla for EthtoolRequestAttr {
+    fn value_len(&self) -> usize {
+        match self {
+            Self::Flag(_) => 0,
+            Self::U8(..) => 1,
+            Self::U32(..) => 4,
+            Self::String(_, value) => value.len() + 1,
+            Self::Nested(_, attrs) => attrs.as_slice().buffer_len(),
+        }
+    }
+
+    fn kind(&self) -> u16 {
+        match self {
+            Self::Flag(kind) | Self::U8(kind, _) | Self::U32(kind, _) | Self::String(kind, _) => {
+                *kind
+            }
+            Self::Nested(kind, _) => kind | NLA_F_NESTED,
+        }
+    }
+
+    fn emit_value(&self, buffer: &mut [u8]) {
+        match self {
+            Self::Flag(_) => {}
+            Self::U8(_, value) => buffer[0] = *value,
+            Self::U32(_, value) => buffer[..4].copy_from_slice(&value.to_ne_bytes()),
+            Self::String(_, value) => {
+                buffer[..value.len()].copy_from_slice(value.as_bytes());
+                buffer[value.len()] = 0;
+            }
+            Self::Nested(_, attrs) => attrs.as_slice().emit(buffer),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EthtoolRequest {
+    cmd: u8,
+    attributes: Vec<EthtoolRequestAttr>,
+}
+
+impl GenlFamily for EthtoolRequest {
+    fn family_name() -> &'static str {
+        "ethtool"
+    }
+
+    fn command(&self) -> u8 {
+        self.cmd
+    }
+
+    fn version(&self) -> u8 {
+        1
+    }
+}
+
+impl Emitable for EthtoolRequest {
+    fn buffer_len(&self) -> usize {
+        self.attributes.as_slice().buffer_len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) {
+        self.attributes.as_slice().emit(buffer)
+    }
+}
+
+// Set requests are only answered with an acknowledgement, so there is no
+// payload to parse
+impl ParseableParametrized<[u8], GenlHeader> for EthtoolRequest {
+    fn parse_with_param(_buf: &[u8], header: GenlHeader) -> Result<Self, DecodeError> {
+        Ok(Self {
+            cmd: header.cmd,
+            attributes: vec![],
+        })
+    }
+}
+
+// Layout of `struct ethtool_drvinfo`, filled in by the kernel
+#[repr(C)]
+#[allow(dead_code)]
+struct EthtoolDrvinfo {
+    cmd: u32,
+    driver: [u8; 32],
+    version: [u8; 32],
+    fw_version: [u8; 32],
+    bus_info: [u8; 32],
+    erom_version: [u8; 32],
+    reserved2: [u8; 12],
+    n_priv_flags: u32,
+    n_stats: u32,
+    testinfo_len: u32,
+    eedump_len: u32,
+    regdump_len: u32,
+}
+
+pub struct EthtoolManager {
+    // Namespace the driver information ioctl has to be issued in
+    netns: Option<String>,
+    ethtool: SupervisedHandle<EthtoolHandle>,
+}
+
+impl EthtoolManager {
+    pub fn try_new(netns: Option<&str>) -> Result<Self> {
+        let health = Arc::new(HealthMonitor::new());
+        let connection = Self::connect(netns)?;
+
+        let netns = netns.map(str::to_owned);
+        let connect_netns = netns.clone();
+        let ethtool = SupervisedHandle::new("ethtool", connection, health, None, move || {
+            Box::pin(future::ready(Self::connect(connect_netns.as_deref())))
+        });
+
+        Ok(Self { netns, ethtool })
+    }
+
+    fn connect(netns: Option<&str>) -> Result<Connection<EthtoolHandle>> {
+        let (connection, handle, _) = enter_namespace(netns, ::ethtool::new_connection)?;
+        Ok(Connection {
+            handle,
+            futures: vec![Box::pin(connection)],
+        })
+    }
+
+    // The handle changes whenever the connection is reestablished, so it
+    // must not be kept around
+    fn handle(&self) -> EthtoolHandle {
+        self.ethtool.get()
+    }
+
+    pub fn health(&self) -> ConnectionHealth {
+        self.ethtool.health()
+    }
+
+    // Devices without ethtool support for a request reject it with
+    // EOPNOTSUPP, which is not treated as a failure
+    fn is_unsupported(error: &EthtoolError) -> bool {
+        match error {
+            EthtoolError::NetlinkError(e) => e.to_io().raw_os_error() == Some(libc::EOPNOTSUPP),
+            _ => false,
+        }
+    }
+
+    pub async fn get_link_modes(&self, interface_name: &str) -> Result<Option<EthtoolLinkModes>> {
+        let mut response = self
+            .handle()
+            .link_mode()
+            .get(Some(interface_name))
+            .execute()
+            .await;
+        let message = match response.try_next().await {
+            Ok(Some(message)) => message,
+            Ok(None) => return Ok(None),
+            Err(e) if Self::is_unsupported(&e) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut link_modes = EthtoolLinkModes {
+            speed: None,
+            duplex: None,
+            autoneg: false,
+        };
+        for attr in message.payload.nlas {
+            match attr {
+                EthtoolAttr::LinkMode(EthtoolLinkModeAttr::Speed(speed)) => {
+                    link_modes.speed = (speed != SPEED_UNKNOWN).then_some(speed);
+                }
+                EthtoolAttr::LinkMode(EthtoolLinkModeAttr::Duplex(duplex)) => {
+                    link_modes.duplex = match duplex {
+                        EthtoolLinkModeDuplex::Half => Some(EthtoolDuplex::Half),
+                        EthtoolLinkModeDuplex::Full => Some(EthtoolDuplex::Full),
+                        _ => None,
+                    };
+                }
+                EthtoolAttr::LinkMode(EthtoolLinkModeAttr::Autoneg(autoneg)) => {
+                    link_modes.autoneg = autoneg;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Some(link_modes))
+    }
+
+    pub async fn get_features(&self, interface_name: &str) -> Result<Vec<EthtoolFeature>> {
+        let mut response = self
+            .handle()
+            .feature()
+            .get(Some(interface_name))
+            .execute()
+            .await;
+        let message = match response.try_next().await {
+            Ok(Some(message)) => message,
+            Ok(None) => return Ok(vec![]),
+            Err(e) if Self::is_unsupported(&e) => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+
+        // Features are reported as sets of names: the ones the hardware
+        // allows to change, the active ones, and the ones that are fixed
+        let mut features = BTreeMap::new();
+        let mut hw = HashSet::new();
+        let mut active = HashSet::new();
+        let mut fixed = HashSet::new();
+        for attr in message.payload.nlas {
+            let EthtoolAttr::Feature(attr) = attr else {
+                continue;
+            };
+
+            match attr {
+                EthtoolFeatureAttr::Hw(bits) => {
+                    for bit in bits {
+                        hw.insert(bit.name.clone());
+                        features.insert(bit.index, bit.name);
+                    }
+                }
+                EthtoolFeatureAttr::Active(bits) => {
+                    for bit in bits {
+                        active.insert(bit.name.clone());
+                        features.insert(bit.index, bit.name);
+                    }
+                }
+                EthtoolFeatureAttr::NoChange(bits) => {
+                    fixed.extend(bits.into_iter().map(|bit| bit.name));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(features
+            .into_values()
+            .map(|name| EthtoolFeature {
+                active: active.contains(&name),
+                changeable: hw.contains(&name) && !fixed.contains(&name),
+                name,
+            })
+            .collect())
+    }
+
+    pub fn get_driver_info(&self, interface_name: &str) -> Result<Option<EthtoolDriverInfo>> {
+        if interface_name.len() >= libc::IFNAMSIZ {
+            return Err(anyhow!("Interface name is too long: {}", interface_name));
+        }
+
+        let socket = enter_namespace(self.netns.as_deref(), || {
+            socket(
+                AddressFamily::Inet,
+                SockType::Datagram,
+                SockFlag::SOCK_CLOEXEC,
+                None,
+            )
+            .map_err(io::Error::from)
+        })?;
+
+        let mut drvinfo = EthtoolDrvinfo {
+            cmd: ETHTOOL_GDRVINFO,
+            driver: [0; 32],
+            version: [0; 32],
+            fw_version: [0; 32],
+            bus_info: [0; 32],
+            erom_version: [0; 32],
+            reserved2: [0; 12],
+            n_priv_flags: 0,
+            n_stats: 0,
+            testinfo_len: 0,
+            eedump_len: 0,
+            regdump_len: 0,
+        };
+
+        // SAFETY: `ifreq` is plain old data, the name is NUL-terminated since
+        // it's shorter than IFNAMSIZ, and `drvinfo` outlives the ioctl call
+        let result = unsafe {
+            let mut request: libc::ifreq = mem::zeroed();
+            for (dst, src) in request.ifr_name.iter_mut().zip(interface_name.bytes()) {
+                *dst = src as libc::c_char;
+            }
+            request.ifr_ifru.ifru_data = (&mut drvinfo as *mut EthtoolDrvinfo).cast();
+            libc::ioctl(socket.as_raw_fd(), libc::SIOCETHTOOL as _, &mut request)
+        };
+        if result < 0 {
+            let e = io::Error::last_os_error();
+            if e.raw_os_error() == Some(libc::EOPNOTSUPP) {
+                return Ok(None);
+            }
+            return Err(e.into());
+        }
+
+        let parse = |bytes: &[u8]| {
+            CStr::from_bytes_until_nul(bytes)
+                .ok()
+                .map(|s| s.to_string_lossy().into_owned())
+                .filter(|s| !s.is_empty())
+        };
+
+        let Some(driver) = parse(&drvinfo.driver) else {
+            return Ok(None);
+        };
+
+        Ok(Some(EthtoolDriverInfo {
+            driver,
+            version: parse(&drvinfo.version),
+            firmware_version: parse(&drvinfo.fw_version),
+            bus_info: parse(&drvinfo.bus_info),
+        }))
+    }
+
+    pub async fn set_settings(
+        &self,
+        interface_name: &str,
+        settings: &EthtoolSettings,
+    ) -> Result<()> {
+        if settings.has_link_modes() {
+            let mut attributes = vec![EthtoolRequestAttr::header(
+                ETHTOOL_A_LINKMODES_HEADER,
+                interface_name,
+            )];
+            if let Some(autoneg) = settings.autoneg {
+                attributes.push(EthtoolRequestAttr::U8(
+                    ETHTOOL_A_LINKMODES_AUTONEG,
+                    autoneg.into(),
+                ));
+            }
+            if let Some(speed) = settings.speed {
+                attributes.push(EthtoolRequestAttr::U32(ETHTOOL_A_LINKMODES_SPEED, speed));
+            }
+            if let Some(duplex) = settings.duplex {
+                let duplex = match duplex {
+                    EthtoolDuplex::Half => DUPLEX_HALF,
+                    EthtoolDuplex::Full => DUPLEX_FULL,
+                };
+                attributes.push(EthtoolRequestAttr::U8(ETHTOOL_A_LINKMODES_DUPLEX, duplex));
+            }
+
+            self.request(EthtoolRequest {
+                cmd: ETHTOOL_MSG_LINKMODES_SET,
+                attributes,
+            })
+            .await?;
+        }
+
+        if !settings.features.is_empty() {
+            // Only the listed bits are changed, the value flag turns them on
+            let bits = settings
+                .features
+                .iter()
+                .map(|(name, enabled)| {
+                    let mut bit = vec![EthtoolRequestAttr::String(
+                        ETHTOOL_A_BITSET_BIT_NAME,
+                        name.to_owned(),
+                    )];
+                    if *enabled {
+                        bit.push(EthtoolRequestAttr::Flag(ETHTOOL_A_BITSET_BIT_VALUE));
+                    }
+                    EthtoolRequestAttr::Nested(ETHTOOL_A_BITSET_BITS_BIT, bit)
+                })
+                .collect();
+
+            self.request(EthtoolRequest {
+                cmd: ETHTOOL_MSG_FEATURES_SET,
+                attributes: vec![
+                    EthtoolRequestAttr::header(ETHTOOL_A_FEATURES_HEADER, interface_name),
+                    EthtoolRequestAttr::Nested(
+                        ETHTOOL_A_FEATURES_WANTED,
+                        vec![EthtoolRequestAttr::Nested(ETHTOOL_A_BITSET_BITS, bits)],
+                    ),
+                ],
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn request(&self, request: EthtoolRequest) -> Result<()> {
+        let mut message = NetlinkMessage::from(GenlMessage::from_payload(request));
+        message.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+
+        let mut responses = self.handle().handle.request(message).await?;
+        while let Some(response) = responses.next().await {
+            if let NetlinkPayload::Error(e) = response?.payload
+                && e.code.is_some()
+            {
+                return Err(e.to_io().into());
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use std::net::IpAddr;
+
+use macaddr::MacAddr;
+
+use crate::service::netlink::{route::RouteInterface, wiphy::WiphyInterface};
+
+// Capacity of the event broadcast channel. Subscribers that fall further
+// behind than this will skip the oldest events.
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone)]
+pub enum NetlinkEvent {
+    // Sent both when a link is created and when its state changes
+    LinkUpdated(RouteInterface),
+    LinkRemoved(RouteInterface),
+    AddressAdded {
+        index: u32,
+        address: IpAddr,
+        prefix_len: u8,
+    },
+    AddressRemoved {
+        index: u32,
+        address: IpAddr,
+        prefix_len: u8,
+    },
+    // Sent both when a neighbour appears and when its state changes
+    NeighbourUpdated {
+        index: u32,
+        ip_address: IpAddr,
+        mac_address: Option<MacAddr>,
+    },
+    NeighbourRemoved {
+        index: u32,
+        ip_address: IpAddr,
+        mac_address: Option<MacAddr>,
+    },
+    RouteAdded {
+        destination: Option<IpAddr>,
+        prefix_len: u8,
+        gateway: Option<IpAddr>,
+        output_index: Option<u32>,
+    },
+    RouteRemoved {
+        destination: Option<IpAddr>,
+        prefix_len: u8,
+        gateway: Option<IpAddr>,
+        output_index: Option<u32>,
+    },
+    // Sent when a wireless interface is created or its mode changes
+    WirelessInterfaceUpdated(WiphyInterface),
+    WirelessInterfaceRemoved(WiphyInterface),
+    WirelessDeviceChanged {
+        phy_index: u32,
+    },
+    StationConnected {
+        index: u32,
+        mac_address: MacAddr,
+    },
+    StationDisconnected {
+        index: u32,
+        mac_address: MacAddr,
+    },
+    // Results of a scan on the interface are ready to be dumped, unless it
+    // was aborted
+    ScanFinished {
+        index: u32,
+        aborted: bool,
+    },
+    // The regulatory domain all devices follow has changed
+    RegulatoryChanged {
+        country: Option<String>,
+    },
+    // Notifications were dropped because the socket buffer was full
+    Overrun,
+    // A dead connection was reestablished, notifications may have been lost
+    Reconnected,
+}
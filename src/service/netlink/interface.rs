@@ -1,28 +1,32 @@
 use crate::service::{
     LinkState,
-    netlink::{
-        route::{RouteInterface, RouteInterfaceKind, RouteManager},
-        wiphy::{WiphyInterface, WiphyManager},
-    },
+    netlink::route::{BridgeOptions, LinkStats, RouteInterface, RouteInterfaceKind},
 };
 use anyhow::{Result, anyhow};
-use futures_util::TryStreamExt;
-use macaddr::MacAddr;
-use rtnetlink::packet_route::{
-    link::{LinkAttribute, LinkFlags, LinkLayerType},
-    neighbour::{NeighbourAddress, NeighbourAttribute},
-};
+use rtnetlink::packet_route::link::{BondMode, LinkFlags, MacVlanMode};
 use serde::{Deserialize, Serialize, Serializer};
-use std::{collections::HashMap, net::IpAddr, str::FromStr};
-use tokio::task::JoinHandle;
-use wl_nl80211::{Nl80211Attr, Nl80211IfMode, Nl80211InterfaceType};
+use wl_nl80211::{
+    Nl80211ChannelWidth, Nl80211IfMode, Nl80211IfaceComb, Nl80211IfaceCombAttribute,
+    Nl80211IfaceCombLimitAttribute, Nl80211InterfaceType,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", content = "value")]
 pub enum NetlinkInterfaceMode {
     Station,
     Monitor,
     AccessPoint,
+    // Member of an independent BSS (IBSS)
+    Adhoc,
+    MeshPoint,
+    Wds,
+    // Per-station VLAN of an access point on the same device
+    ApVlan,
+    P2pClient,
+    P2pGo,
+    // Outside the context of a BSS, as used by vehicular networks
+    Ocb,
+    // Modes without a network interface, such as P2P device and NAN
     #[serde(skip_deserializing)]
     OtherWireless(u32),
 }
@@ -33,6 +37,13 @@ impl From<Nl80211IfMode> for NetlinkInterfaceMode {
             Nl80211IfMode::Station => Self::Station,
             Nl80211IfMode::Monitor => Self::Monitor,
             Nl80211IfMode::Ap => Self::AccessPoint,
+            Nl80211IfMode::Adhoc => Self::Adhoc,
+            Nl80211IfMode::MeshPoint => Self::MeshPoint,
+            Nl80211IfMode::Wds => Self::Wds,
+            Nl80211IfMode::ApVlan => Self::ApVlan,
+            Nl80211IfMode::P2pClient => Self::P2pClient,
+            Nl80211IfMode::P2pGo => Self::P2pGo,
+            Nl80211IfMode::Ocb => Self::Ocb,
             other => Self::OtherWireless(u16::from(other).into()),
         }
     }
@@ -44,6 +55,13 @@ impl From<Nl80211InterfaceType> for NetlinkInterfaceMode {
             Nl80211InterfaceType::Station => Self::Station,
             Nl80211InterfaceType::Monitor => Self::Monitor,
             Nl80211InterfaceType::Ap => Self::AccessPoint,
+            Nl80211InterfaceType::Adhoc => Self::Adhoc,
+            Nl80211InterfaceType::MeshPoint => Self::MeshPoint,
+            Nl80211InterfaceType::Wds => Self::Wds,
+            Nl80211InterfaceType::ApVlan => Self::ApVlan,
+            Nl80211InterfaceType::P2pClient => Self::P2pClient,
+            Nl80211InterfaceType::P2pGo => Self::P2pGo,
+            Nl80211InterfaceType::Ocb => Self::Ocb,
             other => Self::OtherWireless(other.into()),
         }
     }
@@ -56,15 +74,276 @@ impl TryInto<Nl80211InterfaceType> for NetlinkInterfaceMode {
             Self::Station => Nl80211InterfaceType::Station,
             Self::Monitor => Nl80211InterfaceType::Monitor,
             Self::AccessPoint => Nl80211InterfaceType::Ap,
+            Self::Adhoc => Nl80211InterfaceType::Adhoc,
+            Self::MeshPoint => Nl80211InterfaceType::MeshPoint,
+            Self::Wds => Nl80211InterfaceType::Wds,
+            Self::ApVlan => Nl80211InterfaceType::ApVlan,
+            Self::P2pClient => Nl80211InterfaceType::P2pClient,
+            Self::P2pGo => Nl80211InterfaceType::P2pGo,
+            Self::Ocb => Nl80211InterfaceType::Ocb,
             Self::OtherWireless(other) => Nl80211InterfaceType::Other(other),
         })
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum NetlinkChannelWidth {
+    NoHt20,
+    Mhz(u32),
+    Mhz80Plus80,
+}
+
+impl NetlinkChannelWidth {
+    pub fn from_nl80211(value: Nl80211ChannelWidth) -> Option<Self> {
+        match value {
+            Nl80211ChannelWidth::NoHt20 => Some(Self::NoHt20),
+            Nl80211ChannelWidth::Mhz(mhz) => Some(Self::Mhz(mhz)),
+            Nl80211ChannelWidth::Mhz80Plus80 => Some(Self::Mhz80Plus80),
+            _ => None,
+        }
+    }
+
+    // Width of the segment around the first center frequency
+    pub fn mhz(&self) -> u32 {
+        match self {
+            Self::NoHt20 => 20,
+            Self::Mhz(mhz) => *mhz,
+            Self::Mhz80Plus80 => 80,
+        }
+    }
+}
+
+impl From<NetlinkChannelWidth> for Nl80211ChannelWidth {
+    fn from(value: NetlinkChannelWidth) -> Self {
+        match value {
+            NetlinkChannelWidth::NoHt20 => Self::NoHt20,
+            NetlinkChannelWidth::Mhz(mhz) => Self::Mhz(mhz),
+            NetlinkChannelWidth::Mhz80Plus80 => Self::Mhz80Plus80,
+        }
+    }
+}
+
+// Operating channel of a wireless interface. Frequencies are in MHz, and
+// the center ones are those of the whole channel rather than of its primary
+// 20 MHz part.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetlinkChannel {
+    pub frequency: u32,
+    pub channel: Option<u32>,
+    pub width: NetlinkChannelWidth,
+    pub center_frequency: Option<u32>,
+    // Second 80 MHz segment of 80+80 MHz channels
+    pub center_frequency2: Option<u32>,
+}
+
+// Transmit power levels are in mBm, i.e. 100 * dBm
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum NetlinkTxPower {
+    // Chosen by the driver
+    Auto,
+    // Chosen by the driver, up to the level
+    Limited(u32),
+    Fixed(u32),
+}
+
+// Up to `max` interfaces of the given modes
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetlinkInterfaceLimit {
+    pub max: u32,
+    pub modes: Vec<NetlinkInterfaceMode>,
+}
+
+// Interfaces that a wireless device is able to run at the same time
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetlinkInterfaceCombination {
+    pub limits: Vec<NetlinkInterfaceLimit>,
+    pub max_interfaces: u32,
+    // Number of different channels the interfaces can be on
+    pub num_channels: u32,
+    // Channel widths radar detection works with, empty if not supported
+    pub radar_detect_widths: Vec<NetlinkChannelWidth>,
+}
+
+impl From<Nl80211IfaceComb> for NetlinkInterfaceCombination {
+    fn from(value: Nl80211IfaceComb) -> Self {
+        let mut combination = Self {
+            limits: vec![],
+            max_interfaces: 0,
+            num_channels: 0,
+            radar_detect_widths: vec![],
+        };
+
+        for attr in value.attributes {
+            match attr {
+                Nl80211IfaceCombAttribute::Limits(limits) => {
+                    for limit in limits {
+                        let mut max = 0;
+                        let mut modes = vec![];
+                        for attr in limit.attributes {
+                            match attr {
+                                Nl80211IfaceCombLimitAttribute::Max(m) => max = m,
+                                Nl80211IfaceCombLimitAttribute::Iftypes(iftypes) => {
+                                    modes = iftypes.into_iter().map(Into::into).collect();
+                                }
+                                _ => {}
+                            }
+                        }
+                        combination
+                            .limits
+                            .push(NetlinkInterfaceLimit { max, modes });
+                    }
+                }
+                Nl80211IfaceCombAttribute::Maxnum(m) => combination.max_interfaces = m,
+                Nl80211IfaceCombAttribute::NumChannels(n) => combination.num_channels = n,
+                // Bitmask of `enum nl80211_chan_width` values
+                Nl80211IfaceCombAttribute::RadarDetectWidths(widths) => {
+                    combination.radar_detect_widths = (0..u32::BITS)
+                        .filter(|bit| widths & (1 << bit) != 0)
+                        .filter_map(|bit| NetlinkChannelWidth::from_nl80211(bit.into()))
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+
+        combination
+    }
+}
+
+impl NetlinkInterfaceCombination {
+    // Same rules as cfg80211_check_combinations(): all interfaces count
+    // towards the maximum, but software modes don't need to be listed in a
+    // limit. Every limit listing a mode has to fit all interfaces of it.
+    pub fn allows(
+        &self,
+        modes: &[NetlinkInterfaceMode],
+        software: &[NetlinkInterfaceMode],
+    ) -> bool {
+        if modes.len() > self.max_interfaces as usize {
+            return false;
+        }
+
+        let mut remaining = self.limits.iter().map(|x| x.max).collect::<Vec<_>>();
+        let mut counted: Vec<&NetlinkInterfaceMode> = vec![];
+        for mode in modes.iter().filter(|x| !software.contains(x)) {
+            if counted.contains(&mode) {
+                continue;
+            }
+            counted.push(mode);
+
+            let count = modes.iter().filter(|x| *x == mode).count() as u32;
+            let mut listed = false;
+            for (limit, remaining) in self.limits.iter().zip(remaining.iter_mut()) {
+                if !limit.modes.contains(mode) {
+                    continue;
+                }
+                if *remaining < count {
+                    return false;
+                }
+                *remaining -= count;
+                listed = true;
+            }
+
+            if !listed {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum NetlinkMacVlanMode {
+    Private,
+    Vepa,
+    Bridge,
+    Passthrough,
+    Source,
+}
+
+impl From<NetlinkMacVlanMode> for MacVlanMode {
+    fn from(value: NetlinkMacVlanMode) -> Self {
+        match value {
+            NetlinkMacVlanMode::Private => Self::Private,
+            NetlinkMacVlanMode::Vepa => Self::Vepa,
+            NetlinkMacVlanMode::Bridge => Self::Bridge,
+            NetlinkMacVlanMode::Passthrough => Self::Passthrough,
+            NetlinkMacVlanMode::Source => Self::Source,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum NetlinkBondMode {
+    BalanceRr,
+    ActiveBackup,
+    BalanceXor,
+    Broadcast,
+    Ieee8023Ad,
+    BalanceTlb,
+    BalanceAlb,
+}
+
+impl From<NetlinkBondMode> for BondMode {
+    fn from(value: NetlinkBondMode) -> Self {
+        match value {
+            NetlinkBondMode::BalanceRr => Self::BalanceRr,
+            NetlinkBondMode::ActiveBackup => Self::ActiveBackup,
+            NetlinkBondMode::BalanceXor => Self::BalanceXor,
+            NetlinkBondMode::Broadcast => Self::Broadcast,
+            NetlinkBondMode::Ieee8023Ad => Self::Ieee8023Ad,
+            NetlinkBondMode::BalanceTlb => Self::BalanceTlb,
+            NetlinkBondMode::BalanceAlb => Self::BalanceAlb,
+        }
+    }
+}
+
+// Virtual link requested through the API. Parent interfaces are
+// referenced by name and resolved by the NetlinkService.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all_fields = "camelCase")]
+pub enum NetlinkVirtualLink {
+    Bridge,
+    Vlan {
+        parent_name: String,
+        vlan_id: u16,
+    },
+    Veth {
+        peer_name: String,
+    },
+    Dummy,
+    MacVlan {
+        parent_name: String,
+        mode: NetlinkMacVlanMode,
+    },
+    Bond {
+        mode: NetlinkBondMode,
+        miimon: Option<u32>,
+    },
+    // Additional interface on the physical device of a wireless interface
+    Wireless {
+        parent_name: String,
+        mode: NetlinkInterfaceMode,
+    },
+}
+
 #[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct NetlinkInterfaceModeStatus {
     pub active: NetlinkInterfaceMode,
     pub supported: Vec<NetlinkInterfaceMode>,
+    // Modes of the physical device that don't count against its interface
+    // combinations, e.g. monitor
+    pub software: Vec<NetlinkInterfaceMode>,
+    pub combinations: Vec<NetlinkInterfaceCombination>,
+    // 4-address (WDS) frames, required to bridge station mode interfaces
+    pub use_4addr: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,35 +359,134 @@ pub struct NetlinkInterface {
     pub index: u32,
     pub name: String,
     pub kind: RouteInterfaceKind,
+    // Missing when the interface has no route information
     #[serde(serialize_with = "link_flags_serializer")]
-    pub link_flags: LinkFlags,
+    pub link_flags: Option<LinkFlags>,
     pub mode_status: Option<NetlinkInterfaceModeStatus>,
+    #[serde(skip)]
+    pub phy_index: Option<u32>,
+    // Wireless physical device the interface runs on
+    pub phy: Option<String>,
+    // Missing for wireless interfaces that aren't operating
+    pub channel: Option<NetlinkChannel>,
+    // Current transmit power in mBm
+    pub tx_power: Option<u32>,
+    pub stats: Option<LinkStats>,
+    #[serde(skip)]
+    pub controller_index: Option<u32>,
+    pub controller: Option<String>,
+    pub bridge_options: Option<BridgeOptions>,
 }
 
 impl NetlinkInterface {
-    pub fn state(&self) -> LinkState {
-        if self.link_flags & LinkFlags::Up == LinkFlags::Up {
-            LinkState::Up
-        } else {
-            LinkState::Down
-        }
+    pub fn state(&self) -> Option<LinkState> {
+        self.link_flags.map(|link_flags| {
+            if link_flags & LinkFlags::Up == LinkFlags::Up {
+                LinkState::Up
+            } else {
+                LinkState::Down
+            }
+        })
     }
 }
 
-impl Into<RouteInterface> for NetlinkInterface {
-    fn into(self) -> RouteInterface {
-        RouteInterface {
-            index: self.index,
-            name: self.name,
-            kind: self.kind,
-            link_flags: self.link_flags,
-        }
+impl TryFrom<NetlinkInterface> for RouteInterface {
+    type Error = anyhow::Error;
+    fn try_from(value: NetlinkInterface) -> Result<Self, Self::Error> {
+        Ok(Self {
+            link_flags: value
+                .link_flags
+                .ok_or(anyhow!("Interface has no route information: {:?}", value))?,
+            index: value.index,
+            name: value.name,
+            kind: value.kind,
+            stats: value.stats,
+            controller_index: value.controller_index,
+            bridge_options: value.bridge_options,
+        })
     }
 }
 
-fn link_flags_serializer<S: Serializer>(link_flags: &LinkFlags, s: S) -> Result<S::Ok, S::Error> {
-    LinkFlagsStruct {
-        is_up: *link_flags & LinkFlags::Up == LinkFlags::Up,
+fn link_flags_serializer<S: Serializer>(
+    link_flags: &Option<LinkFlags>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    link_flags
+        .map(|link_flags| LinkFlagsStruct {
+            is_up: link_flags & LinkFlags::Up == LinkFlags::Up,
+        })
+        .serialize(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use NetlinkInterfaceMode::{AccessPoint, Monitor, Station};
+
+    #[test]
+    fn software_modes_need_no_limit_but_count_towards_the_maximum() {
+        let combination = NetlinkInterfaceCombination {
+            limits: vec![NetlinkInterfaceLimit {
+                max: 1,
+                modes: vec![Station],
+            }],
+            max_interfaces: 2,
+            num_channels: 1,
+            radar_detect_widths: vec![],
+        };
+
+        assert!(combination.allows(&[Station, Monitor], &[Monitor]));
+        assert!(!combination.allows(&[Station, Monitor], &[]));
+        assert!(!combination.allows(&[Station, Monitor, Monitor], &[Monitor]));
+    }
+
+    #[test]
+    fn limit_listing_several_modes_is_shared() {
+        let combination = NetlinkInterfaceCombination {
+            limits: vec![
+                NetlinkInterfaceLimit {
+                    max: 1,
+                    modes: vec![Station],
+                },
+                NetlinkInterfaceLimit {
+                    max: 2,
+                    modes: vec![AccessPoint, Monitor],
+                },
+            ],
+            max_interfaces: 3,
+            num_channels: 1,
+            radar_detect_widths: vec![],
+        };
+
+        assert!(combination.allows(&[Station, AccessPoint, Monitor], &[]));
+        assert!(combination.allows(&[AccessPoint, AccessPoint], &[]));
+        assert!(!combination.allows(&[AccessPoint, AccessPoint, Monitor], &[]));
+        assert!(!combination.allows(&[Station, Station], &[]));
+    }
+
+    #[test]
+    fn every_limit_listing_a_mode_has_to_fit() {
+        let combination = NetlinkInterfaceCombination {
+            limits: vec![
+                NetlinkInterfaceLimit {
+                    max: 2,
+                    modes: vec![Station, AccessPoint],
+                },
+                NetlinkInterfaceLimit {
+                    max: 1,
+                    modes: vec![AccessPoint],
+                },
+            ],
+            max_interfaces: 4,
+            num_channels: 1,
+            radar_detect_widths: vec![],
+        };
+
+        assert!(combination.allows(&[Station, AccessPoint], &[]));
+        assert!(!combination.allows(&[AccessPoint, AccessPoint], &[]));
+        assert!(!combination.allows(&[Station, Station, AccessPoint], &[]));
+        // Modes that no limit lists are refused
+        assert!(!combination.allows(&[Station, Monitor], &[]));
     }
-    .serialize(s)
 }
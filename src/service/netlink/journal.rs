@@ -0,0 +1,172 @@
+use std::{collections::VecDeque, net::IpAddr, sync::Mutex};
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::service::netlink::{
+    interface::{NetlinkInterface, NetlinkInterfaceMode},
+    route::LinkState,
+};
+
+// How many past events are kept for clients resuming a stream
+const REPLAY_CAPACITY: usize = 1024;
+
+// Network state changes, as reported to API clients
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "value", rename_all_fields = "camelCase")]
+pub enum NetworkEvent {
+    InterfaceAdded {
+        interface: Box<NetlinkInterface>,
+    },
+    InterfaceRemoved {
+        interface_name: String,
+    },
+    LinkStateChanged {
+        interface_name: String,
+        link_state: LinkState,
+    },
+    ModeChanged {
+        interface_name: String,
+        mode: NetlinkInterfaceMode,
+    },
+    AddressAdded {
+        interface_name: String,
+        address: IpAddr,
+        prefix_len: u8,
+    },
+    AddressRemoved {
+        interface_name: String,
+        address: IpAddr,
+        prefix_len: u8,
+    },
+    // Sent both when a neighbour appears and when its state changes
+    NeighbourUpdated {
+        interface_name: String,
+        ip_address: IpAddr,
+        mac_address: Option<String>,
+    },
+    NeighbourRemoved {
+        interface_name: String,
+        ip_address: IpAddr,
+        mac_address: Option<String>,
+    },
+    RouteAdded {
+        destination: Option<IpAddr>,
+        prefix_len: u8,
+        gateway: Option<IpAddr>,
+        interface_name: Option<String>,
+    },
+    RouteRemoved {
+        destination: Option<IpAddr>,
+        prefix_len: u8,
+        gateway: Option<IpAddr>,
+        interface_name: Option<String>,
+    },
+    ClientConnected {
+        interface_name: String,
+        mac_address: String,
+    },
+    ClientDisconnected {
+        interface_name: String,
+        mac_address: String,
+    },
+    // Frequencies, and the power allowed on them, may have changed
+    RegulatoryDomainChanged {
+        country: Option<String>,
+    },
+    // Sent to subscribers that missed events, which should refetch the
+    // whole network state
+    ResyncRequired,
+}
+
+pub type NetworkEventId = u64;
+
+pub struct NetworkEventSubscription {
+    // Recorded events that the subscriber has not seen yet
+    pub backlog: Vec<(NetworkEventId, NetworkEvent)>,
+    // Set when some of the requested events are no longer in the replay buffer
+    pub truncated: bool,
+    pub receiver: broadcast::Receiver<(NetworkEventId, NetworkEvent)>,
+}
+
+struct JournalState {
+    next_id: NetworkEventId,
+    replay: VecDeque<(NetworkEventId, NetworkEvent)>,
+}
+
+// Numbers network events, keeps the most recent ones for replay and
+// broadcasts them to live subscribers
+pub struct EventJournal {
+    state: Mutex<JournalState>,
+    sender: broadcast::Sender<(NetworkEventId, NetworkEvent)>,
+}
+
+impl EventJournal {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(REPLAY_CAPACITY);
+        Self {
+            state: Mutex::new(JournalState {
+                next_id: 1,
+                replay: VecDeque::with_capacity(REPLAY_CAPACITY),
+            }),
+            sender,
+        }
+    }
+
+    pub fn record(&self, event: NetworkEvent) {
+        let Ok(mut state) = self.state.lock() else {
+            log::error!("Failed to acquire lock for event journal");
+            return;
+        };
+
+        let id = state.next_id;
+        state.next_id += 1;
+        if state.replay.len() >= REPLAY_CAPACITY {
+            state.replay.pop_front();
+        }
+        state.replay.push_back((id, event.clone()));
+
+        // Sending while holding the lock keeps subscriptions from seeing an
+        // event both in their backlog and in their receiver
+        let _ = self.sender.send((id, event));
+    }
+
+    // Subscribe to new events, replaying the ones recorded after `last_id`
+    pub fn subscribe(&self, last_id: Option<NetworkEventId>) -> Result<NetworkEventSubscription> {
+        let state = self
+            .state
+            .lock()
+            .map_err(|_| anyhow!("Failed to acquire lock for event journal"))?;
+        let receiver = self.sender.subscribe();
+
+        let Some(last_id) = last_id else {
+            return Ok(NetworkEventSubscription {
+                backlog: vec![],
+                truncated: false,
+                receiver,
+            });
+        };
+
+        let backlog = state
+            .replay
+            .iter()
+            .filter(|(id, _)| *id > last_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        // Events are missing if the oldest one still kept is not the one
+        // right after `last_id`, or if the ID comes from before a restart
+        let oldest_id = state
+            .replay
+            .front()
+            .map(|(id, _)| *id)
+            .unwrap_or(state.next_id);
+        let truncated = last_id.saturating_add(1) < oldest_id || last_id >= state.next_id;
+
+        Ok(NetworkEventSubscription {
+            backlog,
+            truncated,
+            receiver,
+        })
+    }
+}
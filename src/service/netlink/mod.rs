@@ -1,129 +1,439 @@
+mod block;
+mod cache;
+mod ethtool;
+mod events;
 mod interface;
+mod journal;
+mod netns;
+mod phy;
+mod regulatory;
 mod route;
+mod scan;
+mod station;
+mod supervisor;
+mod survey;
+mod tc;
+mod timeout;
+mod traffic;
 mod wiphy;
 
+pub use ethtool::{EthtoolInfo, EthtoolSettings};
 pub use interface::*;
-pub use route::LinkState;
+pub use journal::{NetworkEvent, NetworkEventId, NetworkEventSubscription};
+pub use netns::namespace_path;
+pub use phy::{NetlinkPhy, PhyBandKind, center_frequencies};
+pub use regulatory::{NetlinkRegulatoryDomain, is_valid_country};
+pub use route::{BridgeOptions, LinkState, Neighbour, RouteInterfaceKind};
+pub use scan::NetlinkBss;
+pub use station::NetlinkStation;
+pub use supervisor::ConnectionHealth;
+pub use survey::ChannelRecommendation;
+pub use tc::{AddressLimit, Qdisc, TrafficControlConflict, TrafficDirection, TrafficShaper};
+pub use timeout::{NetlinkTimeout, NetlinkTimeouts};
+pub use traffic::{InterfaceTraffic, TrafficResolution};
+pub use wiphy::WirelessRefusal;
 
 use crate::service::netlink::{
-    route::{RouteInterface, RouteInterfaceKind, RouteManager},
-    wiphy::{WiphyInterface, WiphyManager},
+    block::StationBlocker,
+    cache::InterfaceCache,
+    ethtool::EthtoolManager,
+    events::EVENT_CHANNEL_CAPACITY,
+    journal::EventJournal,
+    phy::frequency_to_channel,
+    route::{RouteInterface, RouteManager, VirtualLinkKind},
+    survey::recommend_channels,
+    timeout::with_deadline,
+    traffic::TrafficSampler,
+    wiphy::{DEAUTH_REASON_CODE, WiphyDevice, WiphyInterface, WiphyManager},
 };
 use anyhow::{Result, anyhow};
-use futures_util::TryStreamExt;
+use chrono::Duration;
 use macaddr::MacAddr;
-use rtnetlink::packet_route::{
-    link::{LinkAttribute, LinkFlags, LinkLayerType},
-    neighbour::{NeighbourAddress, NeighbourAttribute},
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+    sync::Arc,
 };
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, net::IpAddr, ops::Index, str::FromStr};
-use tokio::task::JoinHandle;
-use wl_nl80211::{Nl80211Attr, Nl80211IfMode, Nl80211InterfaceType};
+use tokio::sync::{Mutex, broadcast};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetlinkHealth {
+    pub healthy: bool,
+    pub connections: BTreeMap<&'static str, ConnectionHealth>,
+}
 
 pub struct NetlinkService {
-    wiphy_mgr: WiphyManager,
-    route_mgr: RouteManager,
+    // Named network namespace the service operates in, or `None` for the
+    // namespace of the server itself
+    netns: Option<String>,
+    traffic_sample_interval: Duration,
+    cache_resync_interval: Duration,
+    timeouts: NetlinkTimeouts,
+    // Services for other namespaces, created as they are requested
+    namespaces: Mutex<HashMap<String, Arc<NetlinkService>>>,
+    wiphy_mgr: Arc<WiphyManager>,
+    route_mgr: Arc<RouteManager>,
+    ethtool_mgr: Arc<EthtoolManager>,
+    interface_cache: Arc<InterfaceCache>,
+    traffic_sampler: TrafficSampler,
+    journal: Arc<EventJournal>,
+    station_blocker: StationBlocker,
 }
 
 impl NetlinkService {
-    pub fn try_new() -> Result<Self> {
-        let wiphy_mgr = WiphyManager::try_new()?;
-        let route_mgr = RouteManager::try_new()?;
+    pub async fn try_new(
+        traffic_sample_interval: Duration,
+        cache_resync_interval: Duration,
+        timeouts: NetlinkTimeouts,
+    ) -> Result<Self> {
+        Self::try_new_in_namespace(
+            None,
+            traffic_sample_interval,
+            cache_resync_interval,
+            timeouts,
+        )
+        .await
+    }
+
+    pub async fn try_new_in_namespace(
+        netns: Option<String>,
+        traffic_sample_interval: Duration,
+        cache_resync_interval: Duration,
+        timeouts: NetlinkTimeouts,
+    ) -> Result<Self> {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let wiphy_mgr =
+            Arc::new(WiphyManager::try_new_with_events(netns.as_deref(), events.clone()).await?);
+        let route_mgr = Arc::new(RouteManager::try_new_with_events(
+            netns.as_deref(),
+            events.clone(),
+        )?);
+        let ethtool_mgr = Arc::new(EthtoolManager::try_new(netns.as_deref())?);
+        let journal = Arc::new(EventJournal::new());
+        let interface_cache = Arc::new(
+            InterfaceCache::try_new(
+                wiphy_mgr.clone(),
+                route_mgr.clone(),
+                journal.clone(),
+                events.subscribe(),
+                cache_resync_interval,
+                timeouts.query,
+            )
+            .await?,
+        );
+        let traffic_sampler =
+            TrafficSampler::try_new(netns.as_deref(), traffic_sample_interval, timeouts.query)?;
+        let station_blocker = StationBlocker::new(
+            wiphy_mgr.clone(),
+            events.subscribe(),
+            timeouts.wireless_change,
+        );
 
         Ok(Self {
+            netns,
+            traffic_sample_interval,
+            cache_resync_interval,
+            timeouts,
+            namespaces: Mutex::new(HashMap::new()),
             wiphy_mgr,
             route_mgr,
+            ethtool_mgr,
+            interface_cache,
+            traffic_sampler,
+            journal,
+            station_blocker,
         })
     }
 
+    pub fn get_health(&self) -> NetlinkHealth {
+        let connections = BTreeMap::from([
+            ("nl80211", self.wiphy_mgr.health()),
+            ("ethtool", self.ethtool_mgr.health()),
+            ("rtnetlink", self.route_mgr.health()),
+            ("rtnetlinkTraffic", self.traffic_sampler.health()),
+        ]);
+
+        NetlinkHealth {
+            healthy: connections.values().all(|x| x.connected),
+            connections,
+        }
+    }
+
+    pub fn list_namespaces(&self) -> Result<Vec<String>> {
+        netns::list_namespaces()
+    }
+
+    // Get the service that operates in `netns`, which is this one if `None`
+    pub async fn namespace(self: &Arc<Self>, netns: Option<&str>) -> Result<Arc<Self>> {
+        let Some(netns) = netns else {
+            return Ok(self.clone());
+        };
+        if self.netns.as_deref() == Some(netns) {
+            return Ok(self.clone());
+        }
+
+        let mut namespaces = self.namespaces.lock().await;
+        // Forget the services of namespaces that were deleted
+        namespaces.retain(|name, _| netns::namespace_path(name).is_ok());
+        netns::namespace_path(netns)?;
+
+        if let Some(service) = namespaces.get(netns) {
+            return Ok(service.clone());
+        }
+
+        log::info!("Creating netlink service for network namespace '{}'", netns);
+        let service = Arc::new(
+            Self::try_new_in_namespace(
+                Some(netns.to_owned()),
+                self.traffic_sample_interval,
+                self.cache_resync_interval,
+                self.timeouts,
+            )
+            .await?,
+        );
+        namespaces.insert(netns.to_owned(), service.clone());
+
+        Ok(service)
+    }
+
+    // Receive network state changes as they happen, after the ones that were
+    // recorded since `last_event_id`
+    pub fn subscribe_network_events(
+        &self,
+        last_event_id: Option<NetworkEventId>,
+    ) -> Result<NetworkEventSubscription> {
+        self.journal.subscribe(last_event_id)
+    }
+
     pub async fn get_interfaces(&self) -> Result<Vec<NetlinkInterface>> {
-        // Handle wireless interfaces
-        let wiphy_device_modes = self
-            .wiphy_mgr
-            .get_wiphy_devices()
-            .await?
-            .into_iter()
-            .map(|x| {
-                (
-                    x.phy_index,
-                    x.supported_iftypes
-                        .into_iter()
-                        .map(NetlinkInterfaceMode::from)
-                        .collect::<Vec<_>>(),
-                )
-            })
-            .collect::<HashMap<_, _>>();
-        let wiphy_interfaces = self.wiphy_mgr.get_wiphy_interfaces().await?;
-        let mut interfaces = HashMap::<String, NetlinkInterface>::new();
-
-        for iface in wiphy_interfaces {
-            let supported_modes = wiphy_device_modes
-                .get(&iface.phy_index)
-                .cloned()
-                .unwrap_or_else(|| {
-                    log::error!(
-                        "Failed to get wireless physical device '{}' for interface: '{}'",
-                        iface.phy_index,
-                        iface.name
-                    );
-                    vec![]
-                });
-            let active_mode: NetlinkInterfaceMode = iface.iftype.into();
-
-            interfaces.insert(
-                iface.name.clone(),
-                NetlinkInterface {
-                    index: iface.index,
-                    name: iface.name,
-                    kind: RouteInterfaceKind::Wireless,
-                    link_flags: LinkFlags::empty(),
-                    mode_status: Some(NetlinkInterfaceModeStatus {
-                        active: active_mode,
-                        supported: supported_modes,
-                    }),
-                },
-            );
-        }
-
-        // Handle other interfaces
-        let route_interfaces = self.route_mgr.get_interfaces().await?;
-        for iface in route_interfaces {
-            if let Some(inserted_iface) = interfaces.get_mut(&iface.name) {
-                inserted_iface.link_flags = iface.link_flags;
-                log::debug!(
-                    "Interface '{}' already inserted in the interface map. Its data has been complemented with route information.",
-                    iface.name
-                );
-                continue;
+        let mut interfaces = self.interface_cache.get_interfaces()?;
+        // Statistics change too often to be tracked by events
+        for iface in interfaces.iter_mut() {
+            if let Some(stats) = self.traffic_sampler.get_latest_stats(iface.index) {
+                iface.stats = Some(stats);
             }
+        }
+
+        Ok(interfaces)
+    }
 
-            interfaces.insert(
-                iface.name.clone(),
-                NetlinkInterface {
-                    index: iface.index,
-                    name: iface.name,
-                    kind: iface.kind,
-                    link_flags: iface.link_flags,
-                    mode_status: None,
-                },
-            );
+    // Mutations run in their own task, so that they are never left half-done
+    // when the request that started them is dropped or times out. The cache
+    // is resynced before returning, so that reads made right after them don't
+    // race against the resulting events. Mutations that outlive their
+    // deadline resync the cache once they complete.
+    async fn mutate<F>(&self, operation: &'static str, deadline: Duration, future: F) -> Result<()>
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        let mut task = tokio::spawn(future);
+        let result = with_deadline(operation, deadline, async { (&mut task).await? }).await;
+        if let Err(e) = result {
+            if e.is::<NetlinkTimeout>() {
+                let interface_cache = self.interface_cache.clone();
+                tokio::spawn(async move {
+                    let _ = task.await;
+                    log::info!("Netlink operation '{}' completed late", operation);
+                    if let Err(e) = interface_cache.resync().await {
+                        log::error!("Failed to resync interface cache: {}", e);
+                    }
+                });
+            }
+            return Err(e);
         }
 
-        Ok(interfaces.into_values().collect())
+        if let Err(e) = self.interface_cache.resync().await {
+            log::error!("Failed to resync interface cache: {}", e);
+        }
+        Ok(())
     }
 
     pub async fn get_neighbor_mac_addresses(&self) -> Result<HashMap<IpAddr, MacAddr>> {
-        self.route_mgr.get_neighbor_mac_addresses().await
+        with_deadline(
+            "get neighbours",
+            self.timeouts.query,
+            self.route_mgr.get_neighbor_mac_addresses(),
+        )
+        .await
+    }
+
+    pub async fn get_neighbours(&self) -> Result<Vec<Neighbour>> {
+        with_deadline(
+            "get neighbours",
+            self.timeouts.query,
+            self.route_mgr.get_neighbours(),
+        )
+        .await
     }
 
     pub async fn find_interface_by_name(&self, name: &str) -> Result<NetlinkInterface> {
-        // TODO: Avoid querying all interfaces - can be optimized with filters
-        self.get_interfaces()
-            .await?
+        let mut interface = self.interface_cache.find_interface_by_name(name)?;
+        if let Some(stats) = self.traffic_sampler.get_latest_stats(interface.index) {
+            interface.stats = Some(stats);
+        }
+
+        Ok(interface)
+    }
+
+    pub fn get_interface_traffic(
+        &self,
+        interface: &NetlinkInterface,
+        resolution: TrafficResolution,
+    ) -> Result<InterfaceTraffic> {
+        self.traffic_sampler
+            .get_interface_traffic(interface.index, resolution)
+    }
+
+    pub async fn get_interface_ethtool(&self, interface: &NetlinkInterface) -> Result<EthtoolInfo> {
+        let link_modes = with_deadline(
+            "get link modes",
+            self.timeouts.query,
+            self.ethtool_mgr.get_link_modes(&interface.name),
+        )
+        .await?;
+        let features = with_deadline(
+            "get features",
+            self.timeouts.query,
+            self.ethtool_mgr.get_features(&interface.name),
+        )
+        .await?;
+        let driver_info = self.ethtool_mgr.get_driver_info(&interface.name)?;
+
+        Ok(EthtoolInfo {
+            link_modes,
+            driver_info,
+            features,
+        })
+    }
+
+    pub async fn set_interface_ethtool(
+        &self,
+        interface: &NetlinkInterface,
+        settings: &EthtoolSettings,
+    ) -> Result<()> {
+        let ethtool_mgr = self.ethtool_mgr.clone();
+        let name = interface.name.to_owned();
+        let settings = settings.to_owned();
+        self.mutate(
+            "set ethtool settings",
+            self.timeouts.link_change,
+            async move { ethtool_mgr.set_settings(&name, &settings).await },
+        )
+        .await
+    }
+
+    pub async fn get_qdiscs(&self) -> Result<Vec<Qdisc>> {
+        let mut qdiscs = with_deadline(
+            "get qdiscs",
+            self.timeouts.query,
+            self.route_mgr.get_qdiscs(),
+        )
+        .await?;
+
+        let names = self
+            .interface_cache
+            .get_interfaces()?
+            .into_iter()
+            .map(|x| (x.index, x.name))
+            .collect::<HashMap<_, _>>();
+        for qdisc in qdiscs.iter_mut() {
+            qdisc.interface_name = names.get(&qdisc.index).cloned();
+        }
+
+        Ok(qdiscs)
+    }
+
+    // Name of the device that shapes the incoming traffic of `interface`
+    pub fn get_ingress_shaper_name(&self, interface: &NetlinkInterface) -> String {
+        tc::ifb_name(interface.index)
+    }
+
+    // Sets up `shaper` for the traffic going in `direction`, or removes the
+    // one that was set up if `None`
+    pub async fn set_interface_shaper(
+        &self,
+        interface: &NetlinkInterface,
+        direction: TrafficDirection,
+        shaper: Option<TrafficShaper>,
+    ) -> Result<()> {
+        let route_interface: RouteInterface = interface.to_owned().try_into()?;
+        let route_mgr = self.route_mgr.clone();
+        self.mutate(
+            "set traffic shaper",
+            self.timeouts.link_change,
+            async move {
+                match direction {
+                    TrafficDirection::Egress => {
+                        route_mgr
+                            .set_link_shaper(route_interface.index, shaper.as_ref())
+                            .await
+                    }
+                    TrafficDirection::Ingress => {
+                        route_mgr
+                            .set_link_ingress_shaper(&route_interface, shaper.as_ref())
+                            .await
+                    }
+                }
+            },
+        )
+        .await
+    }
+
+    fn find_route_interface(&self, index: u32) -> Result<RouteInterface> {
+        self.interface_cache
+            .get_interfaces()?
             .into_iter()
-            .find(|x| x.name == name)
-            .ok_or(anyhow!("Could not find interface with name: {}", name))
+            .find(|x| x.index == index)
+            .ok_or(anyhow!("No interface with index {}", index))?
+            .try_into()
+    }
+
+    // Limits the traffic of groups of addresses reached through the interface
+    // with `index`, replacing the limits that were set before
+    pub async fn set_address_limits(
+        &self,
+        index: u32,
+        download: Vec<AddressLimit>,
+        upload: Vec<AddressLimit>,
+    ) -> Result<()> {
+        let route_interface = self.find_route_interface(index)?;
+        let route_mgr = self.route_mgr.clone();
+        self.mutate(
+            "set address limits",
+            self.timeouts.link_change,
+            async move {
+                route_mgr
+                    .check_link_address_limits(
+                        &route_interface,
+                        !download.is_empty(),
+                        !upload.is_empty(),
+                    )
+                    .await?;
+                route_mgr
+                    .set_link_address_limits(route_interface.index, false, &download)
+                    .await?;
+                route_mgr
+                    .set_link_ingress_address_limits(&route_interface, &upload)
+                    .await
+            },
+        )
+        .await
+    }
+
+    // Numbers of download and upload limits in place on the interface with
+    // `index`
+    pub async fn count_address_limits(&self, index: u32) -> Result<(usize, usize)> {
+        let route_interface = self.find_route_interface(index)?;
+        with_deadline("get address limits", self.timeouts.query, async {
+            Ok((
+                self.route_mgr.count_link_address_limits(index).await?,
+                self.route_mgr
+                    .count_link_ingress_address_limits(&route_interface)
+                    .await?,
+            ))
+        })
+        .await
     }
 
     pub async fn set_interface_state(
@@ -131,8 +441,240 @@ impl NetlinkService {
         interface: &NetlinkInterface,
         state: LinkState,
     ) -> Result<()> {
-        let route_interface = interface.to_owned().into();
-        self.route_mgr.set_link_state(&route_interface, state).await
+        let route_interface = interface.to_owned().try_into()?;
+        let route_mgr = self.route_mgr.clone();
+        self.mutate("set link state", self.timeouts.link_change, async move {
+            route_mgr.set_link_state(&route_interface, state).await
+        })
+        .await
+    }
+
+    pub async fn rename_interface(&self, interface: &NetlinkInterface, name: &str) -> Result<()> {
+        let route_interface = interface.to_owned().try_into()?;
+        let route_mgr = self.route_mgr.clone();
+        let name = name.to_owned();
+        self.mutate("rename link", self.timeouts.link_change, async move {
+            route_mgr.rename_link(&route_interface, &name).await
+        })
+        .await
+    }
+
+    pub async fn create_virtual_interface(
+        &self,
+        name: &str,
+        link: NetlinkVirtualLink,
+    ) -> Result<()> {
+        let kind = match link {
+            NetlinkVirtualLink::Bridge => VirtualLinkKind::Bridge,
+            NetlinkVirtualLink::Vlan {
+                parent_name,
+                vlan_id,
+            } => VirtualLinkKind::Vlan {
+                parent_index: self.find_interface_by_name(&parent_name).await?.index,
+                vlan_id,
+            },
+            NetlinkVirtualLink::Veth { peer_name } => VirtualLinkKind::Veth { peer_name },
+            NetlinkVirtualLink::Dummy => VirtualLinkKind::Dummy,
+            NetlinkVirtualLink::MacVlan { parent_name, mode } => VirtualLinkKind::MacVlan {
+                parent_index: self.find_interface_by_name(&parent_name).await?.index,
+                mode: mode.into(),
+            },
+            NetlinkVirtualLink::Bond { mode, miimon } => VirtualLinkKind::Bond {
+                mode: mode.into(),
+                miimon,
+            },
+            NetlinkVirtualLink::Wireless { parent_name, mode } => {
+                let parent = self.find_interface_by_name(&parent_name).await?;
+                return self.create_wireless_interface(name, &parent, mode).await;
+            }
+        };
+
+        let route_mgr = self.route_mgr.clone();
+        let name = name.to_owned();
+        self.mutate("create link", self.timeouts.link_change, async move {
+            route_mgr.create_link(&name, kind).await
+        })
+        .await
+    }
+
+    // Wireless interfaces are created on the physical device of `parent`
+    async fn create_wireless_interface(
+        &self,
+        name: &str,
+        parent: &NetlinkInterface,
+        mode: NetlinkInterfaceMode,
+    ) -> Result<()> {
+        let wiphy_interface = self
+            .get_wiphy_interface(parent)
+            .await?
+            .ok_or(anyhow!("Interface is not wireless: {:?}", parent))?;
+        // The new interface is checked as if it was going to be brought up
+        let wiphy_device = self.get_wiphy_device(wiphy_interface.phy_index).await?;
+        self.check_interface_combination(&wiphy_device, None, &mode)
+            .await?;
+
+        let wiphy_mgr = self.wiphy_mgr.clone();
+        let iftype = mode.try_into()?;
+        let name = name.to_owned();
+        self.mutate(
+            "create wireless interface",
+            self.timeouts.wireless_change,
+            async move {
+                wiphy_mgr
+                    .create_wiphy_interface(&wiphy_device, iftype, name)
+                    .await
+            },
+        )
+        .await
+    }
+
+    // Checks an interface in `mode` against the interface combinations of
+    // its device, along with the running interfaces other than `exclude`
+    async fn check_interface_combination(
+        &self,
+        wiphy_device: &WiphyDevice,
+        exclude: Option<u32>,
+        mode: &NetlinkInterfaceMode,
+    ) -> Result<()> {
+        let interfaces = self.interface_cache.get_interfaces()?;
+        let others = with_deadline(
+            "get wireless interfaces",
+            self.timeouts.query,
+            self.wiphy_mgr.get_wiphy_interfaces(),
+        )
+        .await?
+        .into_iter()
+        .filter(|x| x.phy_index == wiphy_device.phy_index && Some(x.index) != exclude)
+        .filter(|x| {
+            interfaces
+                .iter()
+                .any(|y| y.index == x.index && y.state() == Some(LinkState::Up))
+        })
+        .map(|x| NetlinkInterfaceMode::from(x.iftype))
+        .collect::<Vec<_>>();
+
+        wiphy_device.check_interface(mode, &others)?;
+        Ok(())
+    }
+
+    pub async fn set_interface_controller(
+        &self,
+        interface: &NetlinkInterface,
+        controller: Option<&NetlinkInterface>,
+    ) -> Result<()> {
+        let route_interface = interface.to_owned().try_into()?;
+        let route_controller = controller.map(|x| x.to_owned().try_into()).transpose()?;
+        let route_mgr = self.route_mgr.clone();
+        self.mutate(
+            "set link controller",
+            self.timeouts.link_change,
+            async move {
+                route_mgr
+                    .set_link_controller(&route_interface, route_controller.as_ref())
+                    .await
+            },
+        )
+        .await
+    }
+
+    // Moves the interface into `netns`, or into the namespace of the server
+    // itself if `None`
+    pub async fn set_interface_namespace(
+        &self,
+        interface: &NetlinkInterface,
+        netns: Option<&str>,
+    ) -> Result<()> {
+        let netns_file = netns::open_namespace(netns)?;
+        let route_interface = interface.to_owned().try_into()?;
+        let route_mgr = self.route_mgr.clone();
+        self.mutate(
+            "set link namespace",
+            self.timeouts.link_change,
+            async move {
+                route_mgr
+                    .set_link_namespace(&route_interface, &netns_file)
+                    .await
+            },
+        )
+        .await
+    }
+
+    pub async fn set_bridge_options(
+        &self,
+        bridge: &NetlinkInterface,
+        options: &BridgeOptions,
+    ) -> Result<()> {
+        let route_interface = bridge.to_owned().try_into()?;
+        let route_mgr = self.route_mgr.clone();
+        let options = options.to_owned();
+        self.mutate(
+            "set bridge options",
+            self.timeouts.link_change,
+            async move {
+                route_mgr
+                    .set_bridge_options(&route_interface, &options)
+                    .await
+            },
+        )
+        .await
+    }
+
+    pub async fn set_interface_4addr(
+        &self,
+        interface: &NetlinkInterface,
+        enabled: bool,
+    ) -> Result<()> {
+        let wiphy_interface = self
+            .get_wiphy_interface(interface)
+            .await?
+            .ok_or(anyhow!("Cannot set 4addr for interface: {:?}", interface))?;
+
+        let wiphy_mgr = self.wiphy_mgr.clone();
+        self.mutate("set 4addr", self.timeouts.wireless_change, async move {
+            wiphy_mgr
+                .set_wiphy_interface_4addr(&wiphy_interface, enabled)
+                .await
+        })
+        .await
+    }
+
+    pub async fn delete_virtual_interface(&self, interface: &NetlinkInterface) -> Result<()> {
+        if !interface.kind.is_deletable() {
+            return Err(anyhow!("Interface is not a virtual link: {:?}", interface));
+        }
+
+        // Deleting wireless interfaces through rtnetlink isn't supported
+        if let Some(wiphy_interface) = self.get_wiphy_interface(interface).await? {
+            let remaining = with_deadline(
+                "get wireless interfaces",
+                self.timeouts.query,
+                self.wiphy_mgr.get_wiphy_interfaces(),
+            )
+            .await?
+            .into_iter()
+            .filter(|x| x.phy_index == wiphy_interface.phy_index)
+            .filter(|x| x.index != wiphy_interface.index)
+            .count();
+            if remaining == 0 {
+                return Err(WirelessRefusal::LastInterface.into());
+            }
+
+            let wiphy_mgr = self.wiphy_mgr.clone();
+            return self
+                .mutate(
+                    "delete wireless interface",
+                    self.timeouts.wireless_change,
+                    async move { wiphy_mgr.delete_wiphy_interface(&wiphy_interface).await },
+                )
+                .await;
+        }
+
+        let route_interface = interface.to_owned().try_into()?;
+        let route_mgr = self.route_mgr.clone();
+        self.mutate("delete link", self.timeouts.link_change, async move {
+            route_mgr.delete_link(&route_interface).await
+        })
+        .await
     }
 
     pub async fn set_interface_mode(
@@ -140,16 +682,288 @@ impl NetlinkService {
         interface: &NetlinkInterface,
         mode: NetlinkInterfaceMode,
     ) -> Result<()> {
+        // AP VLAN interfaces only exist on top of an access point, and are
+        // created rather than switched to
+        if mode == NetlinkInterfaceMode::ApVlan {
+            return Err(WirelessRefusal::UnsupportedMode.into());
+        }
+
         let wiphy_interface = self
-            .wiphy_mgr
-            .get_wiphy_interfaces()
+            .get_wiphy_interface(interface)
             .await?
-            .into_iter()
-            .find(|x| x.index == interface.index)
             .ok_or(anyhow!("Cannot set mode for interface: {:?}", interface))?;
+        let wiphy_device = self.get_wiphy_device(wiphy_interface.phy_index).await?;
+        self.check_interface_combination(&wiphy_device, Some(wiphy_interface.index), &mode)
+            .await?;
+
+        let wiphy_mgr = self.wiphy_mgr.clone();
+        let iftype = mode.try_into()?;
+        self.mutate(
+            "set interface mode",
+            self.timeouts.wireless_change,
+            async move {
+                wiphy_mgr
+                    .set_wiphy_interface_mode(&wiphy_interface, iftype)
+                    .await
+            },
+        )
+        .await
+    }
+
+    // Moves the wireless `interface` to the channel around `frequency`, in
+    // MHz. Channels wider than 20 MHz need their center frequency.
+    pub async fn set_channel(
+        &self,
+        interface: &NetlinkInterface,
+        frequency: u32,
+        width: NetlinkChannelWidth,
+        center_frequency: Option<u32>,
+        center_frequency2: Option<u32>,
+    ) -> Result<()> {
+        let wiphy_interface = self
+            .get_wiphy_interface(interface)
+            .await?
+            .ok_or(anyhow!("Cannot set channel for interface: {:?}", interface))?;
+        let phy = NetlinkPhy::from(self.get_wiphy_device(wiphy_interface.phy_index).await?);
+
+        let channel = NetlinkChannel {
+            frequency,
+            channel: frequency_to_channel(frequency),
+            width,
+            center_frequency: center_frequency.or(match width.mhz() {
+                20 => Some(frequency),
+                _ => None,
+            }),
+            center_frequency2,
+        };
+        phy.check_channel(&channel, &wiphy_interface.iftype.into())?;
+
+        let wiphy_mgr = self.wiphy_mgr.clone();
+        let index = wiphy_interface.index;
+        self.mutate("set channel", self.timeouts.wireless_change, async move {
+            wiphy_mgr.set_wiphy_channel(index, &channel).await
+        })
+        .await
+    }
+
+    pub async fn set_tx_power(
+        &self,
+        interface: &NetlinkInterface,
+        tx_power: NetlinkTxPower,
+    ) -> Result<()> {
+        let wiphy_interface = self.get_wiphy_interface(interface).await?.ok_or(anyhow!(
+            "Cannot set TX power for interface: {:?}",
+            interface
+        ))?;
+
+        let wiphy_mgr = self.wiphy_mgr.clone();
+        let index = wiphy_interface.index;
+        self.mutate("set TX power", self.timeouts.wireless_change, async move {
+            wiphy_mgr.set_wiphy_tx_power(index, tx_power).await
+        })
+        .await
+    }
+
+    pub async fn get_phys(&self) -> Result<Vec<NetlinkPhy>> {
+        let devices = with_deadline(
+            "get wireless devices",
+            self.timeouts.query,
+            self.wiphy_mgr.get_wiphy_devices(),
+        )
+        .await?;
+        let wiphy_interfaces = with_deadline(
+            "get wireless interfaces",
+            self.timeouts.query,
+            self.wiphy_mgr.get_wiphy_interfaces(),
+        )
+        .await?;
+
+        let mut phys = devices
+            .into_iter()
+            .map(NetlinkPhy::from)
+            .collect::<Vec<_>>();
+        for phy in phys.iter_mut() {
+            phy.interfaces = wiphy_interfaces
+                .iter()
+                .filter(|x| x.phy_index == phy.index)
+                .map(|x| x.name.clone())
+                .collect();
+            phy.interfaces.sort();
+        }
+        phys.sort_by_key(|x| x.index);
+
+        Ok(phys)
+    }
+
+    pub async fn get_regulatory_domain(&self) -> Result<NetlinkRegulatoryDomain> {
+        with_deadline(
+            "get regulatory domain",
+            self.timeouts.query,
+            self.wiphy_mgr.get_regulatory_domain(),
+        )
+        .await
+    }
+
+    // Sets the country whose regulations all wireless devices follow
+    pub async fn set_regulatory_country(&self, country: &str) -> Result<()> {
+        if !is_valid_country(country) {
+            return Err(anyhow!("Invalid country code: {}", country));
+        }
+
+        let wiphy_mgr = self.wiphy_mgr.clone();
+        let country = country.to_owned();
+        self.mutate(
+            "set regulatory domain",
+            self.timeouts.wireless_change,
+            async move { wiphy_mgr.set_regulatory_country(&country).await },
+        )
+        .await
+    }
+
+    // Scans for wireless networks on `interface`, optionally only for the
+    // given SSIDs and on the given frequencies. Results are sorted by
+    // signal strength.
+    pub async fn scan(
+        &self,
+        interface: &NetlinkInterface,
+        ssids: Vec<String>,
+        frequencies: Vec<u32>,
+    ) -> Result<Vec<NetlinkBss>> {
+        let wiphy_interface = self
+            .get_wiphy_interface(interface)
+            .await?
+            .ok_or(anyhow!("Cannot scan on interface: {:?}", interface))?;
+
+        let mut results = with_deadline(
+            "scan",
+            self.timeouts.scan,
+            self.wiphy_mgr
+                .scan(&wiphy_interface, ssids.clone(), frequencies.clone()),
+        )
+        .await?;
+
+        // The kernel reports every BSS it knows of, not just the scanned ones
+        results.retain(|x| {
+            (ssids.is_empty() || x.ssid.as_ref().is_some_and(|ssid| ssids.contains(ssid)))
+                && (frequencies.is_empty() || frequencies.contains(&x.frequency))
+        });
+        results.sort_by_key(|x| std::cmp::Reverse(x.signal));
+
+        Ok(results)
+    }
+
+    // Scores the channels `interface` could move to, per band. Scanning
+    // first refreshes the survey data of every channel.
+    pub async fn recommend_channels(
+        &self,
+        interface: &NetlinkInterface,
+    ) -> Result<Vec<ChannelRecommendation>> {
+        let wiphy_interface = self
+            .get_wiphy_interface(interface)
+            .await?
+            .ok_or(anyhow!("Cannot survey on interface: {:?}", interface))?;
+        let phy = NetlinkPhy::from(self.get_wiphy_device(wiphy_interface.phy_index).await?);
+
+        let networks = self
+            .scan(interface, vec![], vec![])
+            .await?
+            .into_iter()
+            .filter(|x| !x.associated)
+            .collect::<Vec<_>>();
+        let surveys = with_deadline(
+            "get survey",
+            self.timeouts.query,
+            self.wiphy_mgr.get_survey(wiphy_interface.index),
+        )
+        .await?;
+
+        Ok(recommend_channels(&phy, &surveys, &networks))
+    }
+
+    // Clients connected to the access point `interface`, along with the
+    // addresses they have on it, or on the bridge it is attached to
+    pub async fn get_stations(&self, interface: &NetlinkInterface) -> Result<Vec<NetlinkStation>> {
+        let mut stations = with_deadline(
+            "get stations",
+            self.timeouts.query,
+            self.wiphy_mgr.get_stations(interface.index),
+        )
+        .await?;
+        let neighbours = self.get_neighbours().await?;
+
+        for station in stations.iter_mut() {
+            station.ip_addresses = neighbours
+                .iter()
+                .filter(|x| {
+                    x.index == interface.index || Some(x.index) == interface.controller_index
+                })
+                .filter(|x| x.mac_address.to_string() == station.mac_address)
+                .map(|x| x.ip_address)
+                .collect();
+            station.ip_addresses.sort();
+        }
+        stations.sort_by(|a, b| a.mac_address.cmp(&b.mac_address));
+
+        Ok(stations)
+    }
+
+    // Kicks a station off the access point `interface`, and keeps it from
+    // reconnecting for `block` if given
+    pub async fn deauthenticate_station(
+        &self,
+        interface: &NetlinkInterface,
+        mac_address: MacAddr,
+        reason_code: Option<u16>,
+        block: Option<Duration>,
+    ) -> Result<()> {
+        // Blocked first, so that a station reconnecting right away is caught
+        if let Some(block) = block {
+            self.station_blocker
+                .block(interface.index, mac_address, block);
+        }
+
+        let wiphy_mgr = self.wiphy_mgr.clone();
+        let index = interface.index;
+        let reason_code = reason_code.unwrap_or(DEAUTH_REASON_CODE);
+        let result = self
+            .mutate(
+                "deauthenticate station",
+                self.timeouts.wireless_change,
+                async move { wiphy_mgr.del_station(index, mac_address, reason_code).await },
+            )
+            .await;
+        // A station that could not be deauthenticated is not left blocked
+        if result.is_err() && block.is_some() {
+            self.station_blocker.unblock(index, mac_address);
+        }
+        result
+    }
+
+    async fn get_wiphy_interface(
+        &self,
+        interface: &NetlinkInterface,
+    ) -> Result<Option<WiphyInterface>> {
+        let wiphy_interfaces = with_deadline(
+            "get wireless interfaces",
+            self.timeouts.query,
+            self.wiphy_mgr.get_wiphy_interfaces(),
+        )
+        .await?;
+
+        Ok(wiphy_interfaces
+            .into_iter()
+            .find(|x| x.index == interface.index))
+    }
 
-        self.wiphy_mgr
-            .set_wiphy_interface_mode(&wiphy_interface, mode.try_into()?)
-            .await
+    async fn get_wiphy_device(&self, phy_index: u32) -> Result<WiphyDevice> {
+        with_deadline(
+            "get wireless devices",
+            self.timeouts.query,
+            self.wiphy_mgr.get_wiphy_devices(),
+        )
+        .await?
+        .into_iter()
+        .find(|x| x.phy_index == phy_index)
+        .ok_or(anyhow!("Wireless device {} not found", phy_index))
     }
 }
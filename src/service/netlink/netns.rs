@@ -0,0 +1,79 @@
+use std::{fs::File, io, path::PathBuf};
+
+use anyhow::{Result, anyhow};
+use nix::sched::{CloneFlags, setns};
+use rtnetlink::{NETNS_PATH, SELF_NS_PATH};
+
+// Namespace of the current thread, which may differ from the process one
+const THREAD_NS_PATH: &str = "/proc/thread-self/ns/net";
+
+// Named network namespaces, as created by `ip netns add`
+pub fn list_namespaces() -> Result<Vec<String>> {
+    let entries = match std::fs::read_dir(NETNS_PATH) {
+        Ok(entries) => entries,
+        // The directory only exists once a namespace has been created
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut namespaces = vec![];
+    for entry in entries {
+        if let Some(name) = entry?.file_name().to_str() {
+            namespaces.push(name.to_owned());
+        }
+    }
+    namespaces.sort();
+
+    Ok(namespaces)
+}
+
+pub fn namespace_path(netns: &str) -> Result<PathBuf> {
+    if netns.is_empty() || netns == "." || netns == ".." || netns.contains('/') {
+        return Err(anyhow!("Invalid network namespace name: {}", netns));
+    }
+
+    let path = PathBuf::from(NETNS_PATH).join(netns);
+    if !path.exists() {
+        return Err(anyhow!("Network namespace does not exist: {}", netns));
+    }
+
+    Ok(path)
+}
+
+// Opens the namespace file of `netns`, or of the server's own namespace if
+// it's `None`. The file descriptor can be used to move links into it.
+pub fn open_namespace(netns: Option<&str>) -> Result<File> {
+    let file = match netns {
+        Some(netns) => File::open(namespace_path(netns)?)?,
+        None => File::open(SELF_NS_PATH)?,
+    };
+
+    Ok(file)
+}
+
+// Runs `f` on the current thread while it is inside of `netns`. Sockets keep
+// the namespace they were created in, so this is used for opening netlink
+// connections. `f` must not yield to the runtime, because other tasks could
+// be scheduled on this thread in the meantime.
+pub fn enter_namespace<T>(netns: Option<&str>, f: impl FnOnce() -> io::Result<T>) -> Result<T> {
+    let Some(netns) = netns else {
+        return Ok(f()?);
+    };
+
+    let target = open_namespace(Some(netns))?;
+    let origin = File::open(THREAD_NS_PATH)?;
+    setns(&target, CloneFlags::CLONE_NEWNET)?;
+
+    let result = f();
+
+    // Failing to switch back would leave the thread in the wrong namespace,
+    // which every later request scheduled on it would silently operate on
+    if let Err(e) = setns(&origin, CloneFlags::CLONE_NEWNET) {
+        panic!(
+            "failed to restore the network namespace of the thread: {}",
+            e
+        );
+    }
+
+    Ok(result?)
+}
@@ -0,0 +1,529 @@
+use rtnetlink::packet_core::{Emitable, NlaBuffer, NlasIterator};
+use serde::{Deserialize, Serialize};
+use wl_nl80211::{
+    Nl80211Band, Nl80211BandInfo, Nl80211BandType, Nl80211CipherSuit, Nl80211FrequencyInfo,
+    Nl80211InterfaceType,
+};
+
+use crate::service::netlink::{
+    NetlinkChannel, NetlinkChannelWidth, NetlinkInterfaceCombination, NetlinkInterfaceMode,
+    wiphy::{WiphyDevice, WirelessRefusal},
+};
+
+// Attributes of `NL80211_BAND_ATTR_IFTYPE_DATA` entries, which wl-nl80211
+// parses but doesn't export the types of
+const NL80211_BAND_IFTYPE_ATTR_IFTYPES: u16 = 1;
+const NL80211_BAND_IFTYPE_ATTR_HE_CAP_MAC: u16 = 2;
+const NL80211_BAND_IFTYPE_ATTR_HE_CAP_PHY: u16 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PhyBandKind {
+    Band2GHz,
+    Band5GHz,
+    Band6GHz,
+    Band60GHz,
+    BandS1GHz,
+    Other(u16),
+}
+
+impl From<Nl80211BandType> for PhyBandKind {
+    fn from(value: Nl80211BandType) -> Self {
+        match value {
+            Nl80211BandType::Band2GHz => Self::Band2GHz,
+            Nl80211BandType::Band5GHz => Self::Band5GHz,
+            Nl80211BandType::Band6GHz => Self::Band6GHz,
+            Nl80211BandType::Band60GHz => Self::Band60GHz,
+            Nl80211BandType::BandS1GHz => Self::BandS1GHz,
+            other => Self::Other(other.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhyFrequency {
+    // MHz
+    pub frequency: u32,
+    pub channel: Option<u32>,
+    // mBm, i.e. 100 * dBm
+    pub max_tx_power: Option<u32>,
+    // Not usable in the current regulatory domain
+    pub disabled: bool,
+    // Beaconing and probing aren't allowed, so no AP or IBSS
+    pub no_ir: bool,
+    // Radar detection (DFS) is required
+    pub radar: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhyHtCapabilities {
+    pub capabilities: Vec<String>,
+    // Mbit/s, 0 if not specified by the device
+    pub rx_highest: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhyVhtCapabilities {
+    pub capabilities: Vec<String>,
+    // Mbit/s, 0 if not specified by the device
+    pub rx_highest: u16,
+    pub tx_highest: u16,
+}
+
+// HE capabilities apply to the listed interface modes only. The
+// capability fields are passed on as is, hex encoded.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhyHeCapabilities {
+    pub modes: Vec<NetlinkInterfaceMode>,
+    pub mac_capabilities: String,
+    pub phy_capabilities: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhyBand {
+    pub band: PhyBandKind,
+    pub frequencies: Vec<PhyFrequency>,
+    pub ht: Option<PhyHtCapabilities>,
+    pub vht: Option<PhyVhtCapabilities>,
+    pub he: Vec<PhyHeCapabilities>,
+}
+
+// Wireless physical device, as listed by `iw phy`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetlinkPhy {
+    #[serde(skip)]
+    pub index: u32,
+    pub name: String,
+    pub bands: Vec<PhyBand>,
+    // Highest transmit power of any usable frequency, in mBm
+    pub max_tx_power: Option<u32>,
+    pub cipher_suites: Vec<String>,
+    pub supported_modes: Vec<NetlinkInterfaceMode>,
+    pub software_modes: Vec<NetlinkInterfaceMode>,
+    pub combinations: Vec<NetlinkInterfaceCombination>,
+    // Filled in by the NetlinkService, which knows the interface names
+    pub interfaces: Vec<String>,
+}
+
+impl From<WiphyDevice> for NetlinkPhy {
+    fn from(device: WiphyDevice) -> Self {
+        let mut bands: Vec<PhyBand> = vec![];
+        for band in device.bands.iter() {
+            let kind = PhyBandKind::from(band.kind);
+            match bands.iter_mut().find(|x| x.band == kind) {
+                Some(existing) => merge_band(existing, band),
+                None => {
+                    let mut new_band = PhyBand {
+                        band: kind,
+                        frequencies: vec![],
+                        ht: None,
+                        vht: None,
+                        he: vec![],
+                    };
+                    merge_band(&mut new_band, band);
+                    bands.push(new_band);
+                }
+            }
+        }
+
+        let max_tx_power = bands
+            .iter()
+            .flat_map(|x| x.frequencies.iter())
+            .filter(|x| !x.disabled)
+            .filter_map(|x| x.max_tx_power)
+            .max();
+
+        Self {
+            index: device.phy_index,
+            name: device.phy_name.clone(),
+            bands,
+            max_tx_power,
+            cipher_suites: device.cipher_suites.iter().map(cipher_name).collect(),
+            supported_modes: device.supported_modes(),
+            software_modes: device.software_modes(),
+            combinations: device.interface_combinations,
+            interfaces: vec![],
+        }
+    }
+}
+
+impl NetlinkPhy {
+    fn find_frequency(&self, frequency: u32) -> Option<&PhyFrequency> {
+        self.bands
+            .iter()
+            .flat_map(|x| x.frequencies.iter())
+            .find(|x| x.frequency == frequency)
+    }
+
+    // Channel numbers repeat across bands, so they must be unambiguous
+    pub fn channel_frequency(&self, channel: u32) -> Option<u32> {
+        let mut frequencies = self
+            .bands
+            .iter()
+            .flat_map(|x| x.frequencies.iter())
+            .filter(|x| x.channel == Some(channel))
+            .map(|x| x.frequency);
+        match (frequencies.next(), frequencies.next()) {
+            (Some(frequency), None) => Some(frequency),
+            _ => None,
+        }
+    }
+
+    // Checks that every 20 MHz part of the channel is one the device
+    // supports and the regulatory domain allows for an interface in `mode`
+    pub fn check_channel(
+        &self,
+        channel: &NetlinkChannel,
+        mode: &NetlinkInterfaceMode,
+    ) -> Result<(), WirelessRefusal> {
+        // Beaconing on channels without initiating radiation is forbidden,
+        // and on radar channels needs a channel availability check first,
+        // which is left to hostapd and the like. Clients just follow the AP.
+        let beacons = matches!(
+            mode,
+            NetlinkInterfaceMode::AccessPoint
+                | NetlinkInterfaceMode::P2pGo
+                | NetlinkInterfaceMode::Adhoc
+                | NetlinkInterfaceMode::MeshPoint
+        );
+
+        let width = channel.width.mhz();
+        let center = match channel.center_frequency {
+            Some(center) => center,
+            None if width == 20 => channel.frequency,
+            None => return Err(WirelessRefusal::UnsupportedChannel),
+        };
+
+        let mut segments = vec![(center, width)];
+        match (channel.width, channel.center_frequency2) {
+            (NetlinkChannelWidth::Mhz80Plus80, Some(center2)) => segments.push((center2, 80)),
+            (NetlinkChannelWidth::Mhz80Plus80, None) | (_, Some(_)) => {
+                return Err(WirelessRefusal::UnsupportedChannel);
+            }
+            _ => {}
+        }
+
+        // The primary channel must lie within the first segment
+        if width < 20
+            || channel.frequency.saturating_add(width / 2) < center.saturating_add(10)
+            || channel.frequency.saturating_add(10) > center.saturating_add(width / 2)
+        {
+            return Err(WirelessRefusal::UnsupportedChannel);
+        }
+
+        for (center, width) in segments {
+            let end = center.saturating_add(width / 2);
+            let Some(mut frequency) = center.saturating_add(10).checked_sub(width / 2) else {
+                return Err(WirelessRefusal::UnsupportedChannel);
+            };
+            while frequency < end {
+                let usable = self
+                    .find_frequency(frequency)
+                    .is_some_and(|x| !(x.disabled || (beacons && (x.no_ir || x.radar))));
+                if !usable {
+                    return Err(WirelessRefusal::UnsupportedChannel);
+                }
+                frequency += 20;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Split wiphy dumps may spread the attributes of one band, and even its
+// frequencies, over several messages
+fn merge_band(band: &mut PhyBand, attributes: &Nl80211Band) {
+    for info in attributes.info.iter() {
+        match info {
+            Nl80211BandInfo::Freqs(freqs) => {
+                band.frequencies
+                    .extend(freqs.iter().filter_map(|x| parse_frequency(&x.info)));
+            }
+            Nl80211BandInfo::HtCapa(caps) => {
+                let ht = band.ht.get_or_insert_with(|| PhyHtCapabilities {
+                    capabilities: vec![],
+                    rx_highest: 0,
+                });
+                ht.capabilities = caps.iter_names().map(|(x, _)| x.to_owned()).collect();
+            }
+            Nl80211BandInfo::HtMcsSet(mcs) => {
+                let ht = band.ht.get_or_insert_with(|| PhyHtCapabilities {
+                    capabilities: vec![],
+                    rx_highest: 0,
+                });
+                ht.rx_highest = mcs.rx_highest;
+            }
+            Nl80211BandInfo::VhtCap(caps) => {
+                let vht = band.vht.get_or_insert_with(|| PhyVhtCapabilities {
+                    capabilities: vec![],
+                    rx_highest: 0,
+                    tx_highest: 0,
+                });
+                vht.capabilities = caps.iter_names().map(|(x, _)| x.to_owned()).collect();
+            }
+            Nl80211BandInfo::VhtMcsSet(mcs) => {
+                let vht = band.vht.get_or_insert_with(|| PhyVhtCapabilities {
+                    capabilities: vec![],
+                    rx_highest: 0,
+                    tx_highest: 0,
+                });
+                vht.rx_highest = mcs.rx_highest;
+                vht.tx_highest = mcs.tx_highest;
+            }
+            Nl80211BandInfo::IftypeData(_) => {
+                band.he.extend(parse_he_capabilities(info));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_frequency(info: &[Nl80211FrequencyInfo]) -> Option<PhyFrequency> {
+    let mut frequency = PhyFrequency {
+        frequency: 0,
+        channel: None,
+        max_tx_power: None,
+        disabled: false,
+        no_ir: false,
+        radar: false,
+    };
+
+    for attr in info {
+        match attr {
+            Nl80211FrequencyInfo::Freq(f) => frequency.frequency = *f,
+            Nl80211FrequencyInfo::MaxTxPower(p) => frequency.max_tx_power = Some(*p),
+            Nl80211FrequencyInfo::Disabled => frequency.disabled = true,
+            Nl80211FrequencyInfo::NoIr | Nl80211FrequencyInfo::NoIbss => frequency.no_ir = true,
+            Nl80211FrequencyInfo::Radar => frequency.radar = true,
+            _ => {}
+        }
+    }
+
+    if frequency.frequency == 0 {
+        return None;
+    }
+    frequency.channel = frequency_to_channel(frequency.frequency);
+    Some(frequency)
+}
+
+// The attribute is emitted again and walked through as raw NLAs
+fn parse_he_capabilities(info: &Nl80211BandInfo) -> Vec<PhyHeCapabilities> {
+    let mut buffer = vec![0; info.buffer_len()];
+    info.emit(&mut buffer);
+    let Ok(attribute) = NlaBuffer::new_checked(&buffer[..]) else {
+        return vec![];
+    };
+
+    let mut result = vec![];
+    for entry in NlasIterator::new(attribute.value()).flatten() {
+        let mut modes = vec![];
+        let mut mac_capabilities = None;
+        let mut phy_capabilities = None;
+        for attr in NlasIterator::new(entry.value()).flatten() {
+            match attr.kind() {
+                // Nested flags, each of them an interface type
+                NL80211_BAND_IFTYPE_ATTR_IFTYPES => {
+                    modes = NlasIterator::new(attr.value())
+                        .flatten()
+                        .map(|x| Nl80211InterfaceType::from(u32::from(x.kind())).into())
+                        .collect();
+                }
+                NL80211_BAND_IFTYPE_ATTR_HE_CAP_MAC => mac_capabilities = Some(hex(attr.value())),
+                NL80211_BAND_IFTYPE_ATTR_HE_CAP_PHY => phy_capabilities = Some(hex(attr.value())),
+                _ => {}
+            }
+        }
+
+        if let (Some(mac_capabilities), Some(phy_capabilities)) =
+            (mac_capabilities, phy_capabilities)
+        {
+            result.push(PhyHeCapabilities {
+                modes,
+                mac_capabilities,
+                phy_capabilities,
+            });
+        }
+    }
+
+    result
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+fn cipher_name(cipher: &Nl80211CipherSuit) -> String {
+    match cipher {
+        Nl80211CipherSuit::Other(suite) => format!("{:08x}", suite),
+        other => format!("{:?}", other),
+    }
+}
+
+// Center frequencies of the channels `width` MHz wide that the 20 MHz
+// channel at `frequency` can be the primary one of. 2.4 GHz channels are
+// widened either way, the others are grouped into fixed blocks.
+pub fn center_frequencies(frequency: u32, width: u32) -> Vec<u32> {
+    let lowest = match frequency {
+        _ if width <= 20 => return vec![frequency],
+        2412..=2472 if width == 40 => return vec![frequency + 10, frequency - 10],
+        // Channels 36-144
+        5180..=5720 => 5170,
+        // Channels 149-177
+        5745..=5885 => 5735,
+        5955..=7115 => 5945,
+        _ => return vec![],
+    };
+    vec![lowest + (frequency - lowest) / width * width + width / 2]
+}
+
+// Same mapping as ieee80211_freq_khz_to_channel() in the kernel
+pub fn frequency_to_channel(frequency: u32) -> Option<u32> {
+    match frequency {
+        2484 => Some(14),
+        2407..2484 => Some((frequency - 2407) / 5),
+        4910..=4980 => Some((frequency - 4000) / 5),
+        5935 => Some(2),
+        5955..=7115 => Some((frequency - 5950) / 5),
+        5000..5925 => Some((frequency - 5000) / 5),
+        58320..=70200 => Some((frequency - 56160) / 2160),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use NetlinkInterfaceMode::{AccessPoint, Monitor, Station};
+
+    #[test]
+    fn channels_of_2ghz_band() {
+        assert_eq!(frequency_to_channel(2412), Some(1));
+        assert_eq!(frequency_to_channel(2437), Some(6));
+        assert_eq!(frequency_to_channel(2472), Some(13));
+        assert_eq!(frequency_to_channel(2484), Some(14));
+    }
+
+    #[test]
+    fn channels_of_5ghz_band() {
+        assert_eq!(frequency_to_channel(5180), Some(36));
+        assert_eq!(frequency_to_channel(5825), Some(165));
+        assert_eq!(frequency_to_channel(4920), Some(184));
+    }
+
+    #[test]
+    fn center_channels_of_80_plus_80_segments() {
+        assert_eq!(frequency_to_channel(5210), Some(42));
+        assert_eq!(frequency_to_channel(5530), Some(106));
+        assert_eq!(frequency_to_channel(5775), Some(155));
+    }
+
+    #[test]
+    fn channels_of_6ghz_and_60ghz_bands() {
+        assert_eq!(frequency_to_channel(5935), Some(2));
+        assert_eq!(frequency_to_channel(5955), Some(1));
+        assert_eq!(frequency_to_channel(5985), Some(7));
+        assert_eq!(frequency_to_channel(7115), Some(233));
+        assert_eq!(frequency_to_channel(58320), Some(1));
+        assert_eq!(frequency_to_channel(60480), Some(2));
+    }
+
+    #[test]
+    fn unknown_frequencies_have_no_channel() {
+        assert_eq!(frequency_to_channel(900), None);
+        assert_eq!(frequency_to_channel(5925), None);
+    }
+
+    #[test]
+    fn channel_parts_must_be_supported() {
+        // Channels 36-64, with 52-64 needing radar detection, 100-112, and
+        // 149-165, with 149 not allowing initiating radiation and 165 disabled
+        let frequencies = (5180..=5320)
+            .step_by(20)
+            .chain((5500..=5560).step_by(20))
+            .chain((5745..=5825).step_by(20))
+            .map(|frequency| PhyFrequency {
+                frequency,
+                channel: frequency_to_channel(frequency),
+                max_tx_power: Some(2000),
+                disabled: frequency == 5825,
+                no_ir: frequency == 5745,
+                radar: (5260..=5320).contains(&frequency),
+            })
+            .collect();
+        let phy = NetlinkPhy {
+            index: 0,
+            name: "phy0".to_owned(),
+            bands: vec![PhyBand {
+                band: PhyBandKind::Band5GHz,
+                frequencies,
+                ht: None,
+                vht: None,
+                he: vec![],
+            }],
+            max_tx_power: Some(2000),
+            cipher_suites: vec![],
+            supported_modes: vec![],
+            software_modes: vec![],
+            combinations: vec![],
+            interfaces: vec![],
+        };
+        let check_as = |mode, frequency, width, center_frequency, center_frequency2| {
+            let channel = NetlinkChannel {
+                frequency,
+                channel: frequency_to_channel(frequency),
+                width,
+                center_frequency,
+                center_frequency2,
+            };
+            phy.check_channel(&channel, &mode)
+        };
+        let check = |frequency, width, center_frequency, center_frequency2| {
+            check_as(
+                AccessPoint,
+                frequency,
+                width,
+                center_frequency,
+                center_frequency2,
+            )
+        };
+        let (mhz20, mhz80) = (NetlinkChannelWidth::NoHt20, NetlinkChannelWidth::Mhz(80));
+        let mhz80p80 = NetlinkChannelWidth::Mhz80Plus80;
+
+        assert!(check(5180, mhz20, None, None).is_ok());
+        assert!(check(5200, mhz20, Some(5200), None).is_ok());
+        // Channel 68 isn't supported by the device, and 165 is disabled
+        assert!(check(5340, mhz20, None, None).is_err());
+        assert!(check(5825, mhz20, None, None).is_err());
+
+        // Wide channels need their center, and the primary channel in it
+        assert!(check(5180, mhz80, Some(5210), None).is_ok());
+        assert!(check(5180, mhz80, None, None).is_err());
+        assert!(check(5260, mhz80, Some(5210), None).is_err());
+
+        // Only 80+80 MHz channels have a second segment, and need it
+        assert!(check(5180, mhz80p80, Some(5210), Some(5530)).is_ok());
+        assert!(check(5180, mhz80p80, Some(5210), None).is_err());
+        assert!(check(5180, mhz80, Some(5210), Some(5530)).is_err());
+        // Channels 116-128 of the second segment are missing
+        assert!(check(5180, mhz80p80, Some(5210), Some(5610)).is_err());
+
+        // Channel 149 doesn't allow beaconing
+        assert!(check(5745, mhz20, None, None).is_err());
+        assert!(check(5180, mhz80p80, Some(5210), Some(5775)).is_err());
+        assert!(check_as(Station, 5745, mhz20, None, None).is_ok());
+        assert!(check_as(Monitor, 5745, mhz20, None, None).is_ok());
+
+        // Channels 52-64 can't be beaconed on, but joined
+        let mhz160 = NetlinkChannelWidth::Mhz(160);
+        assert!(check(5180, mhz160, Some(5250), None).is_err());
+        assert!(check_as(Station, 5180, mhz160, Some(5250), None).is_ok());
+        assert!(check_as(Monitor, 5180, mhz160, Some(5250), None).is_ok());
+    }
+}
@@ -0,0 +1,162 @@
+use rtnetlink::packet_core::{Emitable, NlaBuffer, NlasIterator};
+use serde::Serialize;
+use wl_nl80211::Nl80211Attr;
+
+// Attributes of regulatory domains, which wl-nl80211 doesn't know of
+pub const NL80211_ATTR_REG_ALPHA2: u16 = 33;
+const NL80211_ATTR_REG_RULES: u16 = 34;
+const NL80211_ATTR_DFS_REGION: u16 = 146;
+
+const NL80211_ATTR_REG_RULE_FLAGS: u16 = 1;
+const NL80211_ATTR_FREQ_RANGE_START: u16 = 2;
+const NL80211_ATTR_FREQ_RANGE_END: u16 = 3;
+const NL80211_ATTR_FREQ_RANGE_MAX_BW: u16 = 4;
+const NL80211_ATTR_POWER_RULE_MAX_ANT_GAIN: u16 = 5;
+const NL80211_ATTR_POWER_RULE_MAX_EIRP: u16 = 6;
+const NL80211_ATTR_DFS_CAC_TIME: u16 = 7;
+
+// Rule flags, named the way `iw reg get` shows them
+const REG_RULE_FLAGS: [(u32, &str); 18] = [
+    (1 << 0, "NO-OFDM"),
+    (1 << 1, "NO-CCK"),
+    (1 << 2, "NO-INDOOR"),
+    (1 << 3, "NO-OUTDOOR"),
+    (1 << 4, "DFS"),
+    (1 << 5, "PTP-ONLY"),
+    (1 << 6, "PTMP-ONLY"),
+    (1 << 7, "NO-IR"),
+    (1 << 11, "AUTO-BW"),
+    (1 << 12, "IR-CONCURRENT"),
+    (1 << 13, "NO-HT40MINUS"),
+    (1 << 14, "NO-HT40PLUS"),
+    (1 << 15, "NO-80MHZ"),
+    (1 << 16, "NO-160MHZ"),
+    (1 << 17, "NO-HE"),
+    (1 << 18, "NO-320MHZ"),
+    (1 << 19, "NO-EHT"),
+    (1 << 20, "PSD"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum DfsRegion {
+    Fcc,
+    Etsi,
+    Jp,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegulatoryRule {
+    // MHz
+    pub start_frequency: u32,
+    pub end_frequency: u32,
+    pub max_bandwidth: u32,
+    // mBi
+    pub max_antenna_gain: u32,
+    // mBm
+    pub max_eirp: u32,
+    // Milliseconds of channel availability check, if not the default
+    pub dfs_cac_time: Option<u32>,
+    pub flags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetlinkRegulatoryDomain {
+    // ISO 3166-1 alpha-2 code, or "00" for the world domain
+    pub country: String,
+    pub dfs_region: Option<DfsRegion>,
+    pub rules: Vec<RegulatoryRule>,
+}
+
+impl NetlinkRegulatoryDomain {
+    pub fn parse(attributes: &[Nl80211Attr]) -> Option<Self> {
+        let mut country = None;
+        let mut dfs_region = None;
+        let mut rules = vec![];
+        for attr in attributes {
+            let Nl80211Attr::Other(nla) = attr else {
+                continue;
+            };
+            let mut buffer = vec![0; nla.buffer_len()];
+            nla.emit(&mut buffer);
+            let Ok(nla) = NlaBuffer::new_checked(&buffer[..]) else {
+                continue;
+            };
+
+            match nla.kind() {
+                NL80211_ATTR_REG_ALPHA2 => country = parse_country(nla.value()),
+                NL80211_ATTR_DFS_REGION => {
+                    dfs_region = match nla.value().first() {
+                        Some(1) => Some(DfsRegion::Fcc),
+                        Some(2) => Some(DfsRegion::Etsi),
+                        Some(3) => Some(DfsRegion::Jp),
+                        _ => None,
+                    };
+                }
+                NL80211_ATTR_REG_RULES => {
+                    rules = NlasIterator::new(nla.value())
+                        .flatten()
+                        .map(|x| parse_rule(x.value()))
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            country: country?,
+            dfs_region,
+            rules,
+        })
+    }
+}
+
+// Sent NUL terminated
+fn parse_country(value: &[u8]) -> Option<String> {
+    let end = value.iter().position(|x| *x == 0).unwrap_or(value.len());
+    String::from_utf8(value[..end].to_vec()).ok()
+}
+
+fn parse_rule(value: &[u8]) -> RegulatoryRule {
+    let mut rule = RegulatoryRule {
+        start_frequency: 0,
+        end_frequency: 0,
+        max_bandwidth: 0,
+        max_antenna_gain: 0,
+        max_eirp: 0,
+        dfs_cac_time: None,
+        flags: vec![],
+    };
+
+    for attr in NlasIterator::new(value).flatten() {
+        let Some(value) = attr.value().get(..4) else {
+            continue;
+        };
+        let value = u32::from_ne_bytes([value[0], value[1], value[2], value[3]]);
+        match attr.kind() {
+            NL80211_ATTR_REG_RULE_FLAGS => {
+                rule.flags = REG_RULE_FLAGS
+                    .iter()
+                    .filter(|(flag, _)| value & flag != 0)
+                    .map(|(_, name)| (*name).to_owned())
+                    .collect();
+            }
+            // Frequencies and bandwidth are sent in kHz
+            NL80211_ATTR_FREQ_RANGE_START => rule.start_frequency = value / 1000,
+            NL80211_ATTR_FREQ_RANGE_END => rule.end_frequency = value / 1000,
+            NL80211_ATTR_FREQ_RANGE_MAX_BW => rule.max_bandwidth = value / 1000,
+            NL80211_ATTR_POWER_RULE_MAX_ANT_GAIN => rule.max_antenna_gain = value,
+            NL80211_ATTR_POWER_RULE_MAX_EIRP => rule.max_eirp = value,
+            NL80211_ATTR_DFS_CAC_TIME if value > 0 => rule.dfs_cac_time = Some(value),
+            _ => {}
+        }
+    }
+
+    rule
+}
+
+// Country codes are two uppercase letters, apart from the world domain
+pub fn is_valid_country(country: &str) -> bool {
+    country == "00" || (country.len() == 2 && country.chars().all(|x| x.is_ascii_uppercase()))
+}
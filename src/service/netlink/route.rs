@@ -1,20 +1,41 @@
-use std::{collections::HashMap, net::IpAddr, str::FromStr};
+use std::{collections::HashMap, fs::File, net::IpAddr, os::fd::AsRawFd, str::FromStr, sync::Arc};
 
 use anyhow::{Result, anyhow};
-use axum::routing::RouterIntoService;
-use futures_util::TryStreamExt;
+use futures_util::{StreamExt, TryStreamExt, future};
 use macaddr::MacAddr;
+use nix::libc;
 use rtnetlink::{
-    LinkUnspec,
+    LinkBond, LinkBridge, LinkDummy, LinkMacVlan, LinkMessageBuilder, LinkUnspec, LinkVeth,
+    LinkVlan, MulticastGroup,
+    packet_core::{
+        NLM_F_ACK, NLM_F_CREATE, NLM_F_EXCL, NLM_F_REQUEST, NetlinkMessage, NetlinkPayload,
+    },
     packet_route::{
-        link::{LinkAttribute, LinkFlags, LinkHeader, LinkLayerType, LinkMessage, State},
-        neighbour::{NeighbourAddress, NeighbourAttribute},
+        RouteNetlinkMessage,
+        address::{AddressAttribute, AddressMessage},
+        link::{
+            BondMode, InfoBridge, InfoData, InfoKind, LinkAttribute, LinkFlags, LinkInfo,
+            LinkLayerType, LinkMessage, MacVlanMode, Stats64,
+        },
+        neighbour::{NeighbourAddress, NeighbourAttribute, NeighbourMessage},
+        route::{RouteAddress, RouteAttribute, RouteMessage},
+        tc::{TcAttribute, TcFilterU32Option, TcHandle, TcMessage, TcOption},
     },
 };
 use serde::{Deserialize, Serialize};
-use tokio::task::JoinHandle;
+use tokio::sync::broadcast;
 
-#[derive(Serialize, Deserialize)]
+use crate::service::netlink::{
+    events::NetlinkEvent,
+    netns::enter_namespace,
+    supervisor::{Connection, ConnectionHealth, HealthMonitor, SupervisedHandle},
+    tc::{
+        self, ADDRESS_CLASS_BASE, AddressLimit, ETH_P_ALL, INGRESS_HANDLE, Qdisc, SHAPER_CLASS,
+        SHAPER_HANDLE, SHAPER_LEAF_HANDLE, TrafficControlConflict, TrafficShaper,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum LinkState {
     Down,
     Up,
@@ -25,56 +46,411 @@ pub enum RouteInterfaceKind {
     Ethernet,
     Wireless,
     Loopback,
+    Bridge,
+    Vlan,
+    Veth,
+    Dummy,
+    MacVlan,
+    Bond,
+    OtherVirtual(String),
     Unknown(u16),
 }
 
+impl RouteInterfaceKind {
+    pub fn is_virtual(&self) -> bool {
+        matches!(
+            self,
+            Self::Bridge
+                | Self::Vlan
+                | Self::Veth
+                | Self::Dummy
+                | Self::MacVlan
+                | Self::Bond
+                | Self::OtherVirtual(_)
+        )
+    }
+
+    // Wireless interfaces are virtual too, on top of their physical device
+    pub fn is_deletable(&self) -> bool {
+        self.is_virtual() || matches!(self, Self::Wireless)
+    }
+}
+
+impl From<InfoKind> for RouteInterfaceKind {
+    fn from(value: InfoKind) -> Self {
+        match value {
+            InfoKind::Bridge => Self::Bridge,
+            InfoKind::Vlan => Self::Vlan,
+            InfoKind::Veth => Self::Veth,
+            InfoKind::Dummy => Self::Dummy,
+            InfoKind::MacVlan => Self::MacVlan,
+            InfoKind::Bond => Self::Bond,
+            other => Self::OtherVirtual(other.to_string()),
+        }
+    }
+}
+
+// Virtual links that can be created through rtnetlink, along with
+// their type-specific options
+#[derive(Debug, Clone)]
+pub enum VirtualLinkKind {
+    Bridge,
+    Vlan {
+        parent_index: u32,
+        vlan_id: u16,
+    },
+    Veth {
+        peer_name: String,
+    },
+    Dummy,
+    MacVlan {
+        parent_index: u32,
+        mode: MacVlanMode,
+    },
+    Bond {
+        mode: BondMode,
+        miimon: Option<u32>,
+    },
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkStats {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+}
+
+impl From<Stats64> for LinkStats {
+    fn from(value: Stats64) -> Self {
+        Self {
+            rx_bytes: value.rx_bytes,
+            tx_bytes: value.tx_bytes,
+            rx_packets: value.rx_packets,
+            tx_packets: value.tx_packets,
+            rx_errors: value.rx_errors,
+            tx_errors: value.tx_errors,
+            rx_dropped: value.rx_dropped,
+            tx_dropped: value.tx_dropped,
+        }
+    }
+}
+
+// Bridge options as reported by IFLA_INFO_DATA. When updating a bridge,
+// only the options that are set are changed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeOptions {
+    pub stp_enabled: Option<bool>,
+    // Forward delay in centiseconds
+    pub forward_delay: Option<u32>,
+    pub vlan_filtering: Option<bool>,
+}
+
+impl From<Vec<InfoBridge>> for BridgeOptions {
+    fn from(value: Vec<InfoBridge>) -> Self {
+        let mut options = Self::default();
+        for info in value {
+            match info {
+                InfoBridge::StpState(state) => options.stp_enabled = Some(state != 0),
+                InfoBridge::ForwardDelay(delay) => options.forward_delay = Some(delay),
+                InfoBridge::VlanFiltering(enabled) => options.vlan_filtering = Some(enabled),
+                _ => {}
+            }
+        }
+        options
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RouteInterface {
     pub index: u32,
     pub name: String,
     pub kind: RouteInterfaceKind,
     pub link_flags: LinkFlags,
+    pub stats: Option<LinkStats>,
+    pub controller_index: Option<u32>,
+    pub bridge_options: Option<BridgeOptions>,
+}
+
+// Entry of the neighbour table that resolved to a MAC address
+#[derive(Debug, Clone)]
+pub struct Neighbour {
+    pub index: u32,
+    pub ip_address: IpAddr,
+    pub mac_address: MacAddr,
 }
 
 pub struct RouteManager {
-    rtnetlink_future: JoinHandle<()>,
-    rtnetlink: rtnetlink::Handle,
+    rtnetlink: SupervisedHandle<rtnetlink::Handle>,
 }
 
 impl RouteManager {
-    pub fn try_new() -> Result<Self> {
-        let (connection, rtnetlink, _) = rtnetlink::new_connection()?;
-        let rtnetlink_future = tokio::spawn(connection);
-        Ok(Self {
-            rtnetlink_future,
-            rtnetlink,
+    pub fn try_new(netns: Option<&str>) -> Result<Self> {
+        Self::try_new_inner(netns, None)
+    }
+
+    // Same as `try_new`, but also subscribes to link, address, neighbour and
+    // route notifications, which are parsed and sent through `events`
+    pub fn try_new_with_events(
+        netns: Option<&str>,
+        events: broadcast::Sender<NetlinkEvent>,
+    ) -> Result<Self> {
+        Self::try_new_inner(netns, Some(events))
+    }
+
+    fn try_new_inner(
+        netns: Option<&str>,
+        events: Option<broadcast::Sender<NetlinkEvent>>,
+    ) -> Result<Self> {
+        let health = Arc::new(HealthMonitor::new());
+        let connection = Self::connect(netns, events.clone(), health.clone())?;
+
+        let netns = netns.map(str::to_owned);
+        let rtnetlink = SupervisedHandle::new(
+            "rtnetlink",
+            connection,
+            health.clone(),
+            events.clone(),
+            move || {
+                let connection = Self::connect(netns.as_deref(), events.clone(), health.clone());
+                Box::pin(future::ready(connection))
+            },
+        );
+
+        Ok(Self { rtnetlink })
+    }
+
+    fn connect(
+        netns: Option<&str>,
+        events: Option<broadcast::Sender<NetlinkEvent>>,
+        health: Arc<HealthMonitor>,
+    ) -> Result<Connection<rtnetlink::Handle>> {
+        let Some(events) = events else {
+            let (connection, handle, _) = enter_namespace(netns, rtnetlink::new_connection)?;
+            return Ok(Connection {
+                handle,
+                futures: vec![Box::pin(connection)],
+            });
+        };
+
+        let (connection, handle, mut messages) = enter_namespace(netns, || {
+            rtnetlink::new_multicast_connection(&[
+                MulticastGroup::Link,
+                MulticastGroup::Ipv4Ifaddr,
+                MulticastGroup::Ipv6Ifaddr,
+                MulticastGroup::Neigh,
+                MulticastGroup::Ipv4Route,
+                MulticastGroup::Ipv6Route,
+            ])
+        })?;
+        let messages_future = async move {
+            while let Some((message, _)) = messages.next().await {
+                let event = match message.payload {
+                    NetlinkPayload::InnerMessage(message) => Self::parse_event(message),
+                    // The socket buffer was full and notifications were dropped
+                    NetlinkPayload::Overrun(_) => {
+                        health.overrun();
+                        Some(NetlinkEvent::Overrun)
+                    }
+                    _ => None,
+                };
+
+                let Some(event) = event else {
+                    continue;
+                };
+
+                log::trace!("Received rtnetlink event: {:?}", event);
+                // Sending only fails when nobody is subscribed, which is fine
+                let _ = events.send(event);
+            }
+
+            log::warn!("rtnetlink event stream has ended");
+        };
+
+        Ok(Connection {
+            handle,
+            futures: vec![Box::pin(connection), Box::pin(messages_future)],
         })
     }
 
+    // The handle changes whenever the connection is reestablished, so it
+    // must not be kept around
+    fn handle(&self) -> rtnetlink::Handle {
+        self.rtnetlink.get()
+    }
+
+    pub fn health(&self) -> ConnectionHealth {
+        self.rtnetlink.health()
+    }
+
+    fn parse_event(message: RouteNetlinkMessage) -> Option<NetlinkEvent> {
+        match message {
+            RouteNetlinkMessage::NewLink(link) => {
+                Self::parse_link(link).map(NetlinkEvent::LinkUpdated)
+            }
+            RouteNetlinkMessage::DelLink(link) => {
+                Self::parse_link(link).map(NetlinkEvent::LinkRemoved)
+            }
+            RouteNetlinkMessage::NewAddress(address) => {
+                let (index, address, prefix_len) = Self::parse_address(address)?;
+                Some(NetlinkEvent::AddressAdded {
+                    index,
+                    address,
+                    prefix_len,
+                })
+            }
+            RouteNetlinkMessage::DelAddress(address) => {
+                let (index, address, prefix_len) = Self::parse_address(address)?;
+                Some(NetlinkEvent::AddressRemoved {
+                    index,
+                    address,
+                    prefix_len,
+                })
+            }
+            RouteNetlinkMessage::NewNeighbour(neighbour) => {
+                let index = neighbour.header.ifindex;
+                let (ip_address, mac_address) = Self::parse_neighbour(neighbour);
+                Some(NetlinkEvent::NeighbourUpdated {
+                    index,
+                    ip_address: ip_address?,
+                    mac_address,
+                })
+            }
+            RouteNetlinkMessage::DelNeighbour(neighbour) => {
+                let index = neighbour.header.ifindex;
+                let (ip_address, mac_address) = Self::parse_neighbour(neighbour);
+                Some(NetlinkEvent::NeighbourRemoved {
+                    index,
+                    ip_address: ip_address?,
+                    mac_address,
+                })
+            }
+            RouteNetlinkMessage::NewRoute(route) => {
+                let (destination, prefix_len, gateway, output_index) = Self::parse_route(route);
+                Some(NetlinkEvent::RouteAdded {
+                    destination,
+                    prefix_len,
+                    gateway,
+                    output_index,
+                })
+            }
+            RouteNetlinkMessage::DelRoute(route) => {
+                let (destination, prefix_len, gateway, output_index) = Self::parse_route(route);
+                Some(NetlinkEvent::RouteRemoved {
+                    destination,
+                    prefix_len,
+                    gateway,
+                    output_index,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_address(address: AddressMessage) -> Option<(u32, IpAddr, u8)> {
+        let mut ip_address = None;
+        for attr in address.attributes {
+            match attr {
+                // IFA_LOCAL is the actual address on point-to-point links,
+                // where IFA_ADDRESS holds the peer address
+                AddressAttribute::Local(ip) => ip_address = Some(ip),
+                AddressAttribute::Address(ip) => {
+                    ip_address.get_or_insert(ip);
+                }
+                _ => {}
+            }
+        }
+
+        Some((address.header.index, ip_address?, address.header.prefix_len))
+    }
+
+    fn parse_route(route: RouteMessage) -> (Option<IpAddr>, u8, Option<IpAddr>, Option<u32>) {
+        let route_address = |address| match address {
+            RouteAddress::Inet(ip) => Some(IpAddr::V4(ip)),
+            RouteAddress::Inet6(ip) => Some(IpAddr::V6(ip)),
+            _ => None,
+        };
+
+        let mut destination = None;
+        let mut gateway = None;
+        let mut output_index = None;
+        for attr in route.attributes {
+            match attr {
+                RouteAttribute::Destination(address) => destination = route_address(address),
+                RouteAttribute::Gateway(address) => gateway = route_address(address),
+                RouteAttribute::Oif(index) => output_index = Some(index),
+                _ => {}
+            }
+        }
+
+        (
+            destination,
+            route.header.destination_prefix_length,
+            gateway,
+            output_index,
+        )
+    }
+
     pub async fn get_interfaces(&self) -> Result<Vec<RouteInterface>> {
-        let mut links = self.rtnetlink.link().get().execute();
+        let mut links = self.handle().link().get().execute();
         let mut interfaces = Vec::new();
 
         while let Some(link) = links.try_next().await? {
-            let index = link.header.index;
-            let mut ifname = None;
-            let mut link_flags = link.header.flags;
-
-            for attr in link.attributes {
-                match attr {
-                    LinkAttribute::IfName(name) => ifname = Some(name),
-                    _ => {}
+            if let Some(interface) = Self::parse_link(link) {
+                interfaces.push(interface);
+            }
+        }
+
+        Ok(interfaces)
+    }
+
+    fn parse_link(link: LinkMessage) -> Option<RouteInterface> {
+        let index = link.header.index;
+        let mut ifname = None;
+        let mut stats = None;
+        let mut info_kind = None;
+        let mut controller_index = None;
+        let mut bridge_options = None;
+        let link_flags = link.header.flags;
+
+        for attr in link.attributes {
+            match attr {
+                LinkAttribute::IfName(name) => ifname = Some(name),
+                LinkAttribute::Stats64(stats64) => stats = Some(stats64.into()),
+                LinkAttribute::Controller(index) => controller_index = Some(index),
+                LinkAttribute::LinkInfo(infos) => {
+                    for info in infos {
+                        match info {
+                            LinkInfo::Kind(kind) => info_kind = Some(kind),
+                            LinkInfo::Data(InfoData::Bridge(infos)) => {
+                                bridge_options = Some(infos.into())
+                            }
+                            _ => {}
+                        }
+                    }
                 }
+                _ => {}
             }
+        }
 
-            let Some(ifname) = ifname else {
-                log::warn!("Unnamed interface found! Index: {}", index);
-                continue;
-            };
+        let Some(ifname) = ifname else {
+            log::warn!("Unnamed interface found! Index: {}", index);
+            return None;
+        };
 
-            log::trace!("Found interface: {}", ifname);
+        log::trace!("Found interface: {}", ifname);
 
-            let kind = match link.header.link_layer_type {
+        // Virtual links report their actual kind through IFLA_LINKINFO
+        let kind = if let Some(info_kind) = info_kind {
+            info_kind.into()
+        } else {
+            match link.header.link_layer_type {
                 LinkLayerType::Ether => RouteInterfaceKind::Ethernet,
                 LinkLayerType::Loopback => RouteInterfaceKind::Loopback,
                 LinkLayerType::Ieee80211
@@ -84,52 +460,28 @@ impl RouteManager {
                     log::warn!("Unknown interface kind: {other:?}");
                     RouteInterfaceKind::Unknown(other as u16)
                 }
-            };
-
-            interfaces.push(RouteInterface {
-                index,
-                name: ifname,
-                kind,
-                link_flags,
-            });
-        }
+            }
+        };
 
-        Ok(interfaces)
+        Some(RouteInterface {
+            index,
+            name: ifname,
+            kind,
+            link_flags,
+            stats,
+            controller_index,
+            bridge_options,
+        })
     }
 
     pub async fn get_neighbor_mac_addresses(&self) -> Result<HashMap<IpAddr, MacAddr>> {
         let mut address_map = HashMap::new();
 
-        let mut neighbours = self.rtnetlink.neighbours().get().execute();
+        let mut neighbours = self.handle().neighbours().get().execute();
         while let Some(route) = neighbours.try_next().await? {
             log::trace!("Current route: {:?}", route);
 
-            let mut ip_address = None;
-            let mut mac_address = None;
-            for attr in route.attributes.into_iter() {
-                match attr {
-                    NeighbourAttribute::Destination(NeighbourAddress::Inet(ip)) => {
-                        log::trace!("Route IPv4 address: {:?}", ip);
-                        ip_address = Some(IpAddr::V4(ip.to_owned()));
-                    }
-                    NeighbourAttribute::Destination(NeighbourAddress::Inet6(ip)) => {
-                        log::trace!("Route IPv6 address: {:?}", ip);
-                        ip_address = Some(IpAddr::V6(ip.to_owned()));
-                    }
-                    NeighbourAttribute::LinkLocalAddress(addr) => {
-                        log::trace!("LinkLocalAddress: {:?}", addr);
-                        let mac_str = addr
-                            .into_iter()
-                            .map(|byte| format!("{:02X}", byte))
-                            .collect::<Vec<_>>()
-                            .join(":");
-                        mac_address = MacAddr::from_str(mac_str.as_str()).ok();
-                    }
-                    _ => {
-                        continue;
-                    }
-                }
-            }
+            let (ip_address, mac_address) = Self::parse_neighbour(route);
 
             let Some(ip_address) = ip_address else {
                 log::trace!("No IP address in route, skipping...");
@@ -147,12 +499,62 @@ impl RouteManager {
         Ok(address_map)
     }
 
+    pub async fn get_neighbours(&self) -> Result<Vec<Neighbour>> {
+        let mut result = vec![];
+
+        let mut neighbours = self.handle().neighbours().get().execute();
+        while let Some(neighbour) = neighbours.try_next().await? {
+            let index = neighbour.header.ifindex;
+            if let (Some(ip_address), Some(mac_address)) = Self::parse_neighbour(neighbour) {
+                result.push(Neighbour {
+                    index,
+                    ip_address,
+                    mac_address,
+                });
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn parse_neighbour(neighbour: NeighbourMessage) -> (Option<IpAddr>, Option<MacAddr>) {
+        let mut ip_address = None;
+        let mut mac_address = None;
+
+        for attr in neighbour.attributes.into_iter() {
+            match attr {
+                NeighbourAttribute::Destination(NeighbourAddress::Inet(ip)) => {
+                    log::trace!("Route IPv4 address: {:?}", ip);
+                    ip_address = Some(IpAddr::V4(ip.to_owned()));
+                }
+                NeighbourAttribute::Destination(NeighbourAddress::Inet6(ip)) => {
+                    log::trace!("Route IPv6 address: {:?}", ip);
+                    ip_address = Some(IpAddr::V6(ip.to_owned()));
+                }
+                NeighbourAttribute::LinkLocalAddress(addr) => {
+                    log::trace!("LinkLocalAddress: {:?}", addr);
+                    let mac_str = addr
+                        .into_iter()
+                        .map(|byte| format!("{:02X}", byte))
+                        .collect::<Vec<_>>()
+                        .join(":");
+                    mac_address = MacAddr::from_str(mac_str.as_str()).ok();
+                }
+                _ => {
+                    continue;
+                }
+            }
+        }
+
+        (ip_address, mac_address)
+    }
+
     pub async fn set_link_state(
         &self,
         route_interface: &RouteInterface,
         state: LinkState,
     ) -> Result<()> {
-        self.rtnetlink
+        self.handle()
             .link()
             .set(match state {
                 LinkState::Down => LinkUnspec::new_with_index(route_interface.index)
@@ -167,10 +569,582 @@ impl RouteManager {
 
         Ok(())
     }
+
+    pub async fn rename_link(&self, route_interface: &RouteInterface, name: &str) -> Result<()> {
+        // Interfaces can only be renamed while they are down
+        let was_up = route_interface.link_flags.contains(LinkFlags::Up);
+        if was_up {
+            self.set_link_state(route_interface, LinkState::Down)
+                .await?;
+        }
+
+        let result = self
+            .handle()
+            .link()
+            .set(
+                LinkUnspec::new_with_index(route_interface.index)
+                    .name(name.to_owned())
+                    .build(),
+            )
+            .execute()
+            .await;
+
+        // Restore the previous state even if the rename failed. The outcome
+        // of the rename is what is reported either way.
+        if was_up && let Err(e) = self.set_link_state(route_interface, LinkState::Up).await {
+            log::error!(
+                "Failed to bring link {} back up after renaming it: {}",
+                route_interface.index,
+                e
+            );
+        }
+
+        Ok(result?)
+    }
+
+    pub async fn create_link(&self, name: &str, kind: VirtualLinkKind) -> Result<()> {
+        let message = match kind {
+            VirtualLinkKind::Bridge => LinkBridge::new(name).build(),
+            VirtualLinkKind::Vlan {
+                parent_index,
+                vlan_id,
+            } => LinkVlan::new(name, parent_index, vlan_id).build(),
+            VirtualLinkKind::Veth { peer_name } => LinkVeth::new(name, &peer_name).build(),
+            VirtualLinkKind::Dummy => LinkDummy::new(name).build(),
+            VirtualLinkKind::MacVlan { parent_index, mode } => {
+                LinkMacVlan::new(name, parent_index, mode).build()
+            }
+            VirtualLinkKind::Bond { mode, miimon } => {
+                let mut builder = LinkBond::new(name).mode(mode);
+                if let Some(miimon) = miimon {
+                    builder = builder.miimon(miimon);
+                }
+                builder.build()
+            }
+        };
+
+        self.handle().link().add(message).execute().await?;
+
+        Ok(())
+    }
+
+    pub async fn set_link_controller(
+        &self,
+        route_interface: &RouteInterface,
+        controller: Option<&RouteInterface>,
+    ) -> Result<()> {
+        let builder = LinkUnspec::new_with_index(route_interface.index);
+        self.handle()
+            .link()
+            .set(match controller {
+                Some(controller) => builder.controller(controller.index).build(),
+                None => builder.nocontroller().build(),
+            })
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    // Moves the link into the namespace that `netns_file` refers to
+    pub async fn set_link_namespace(
+        &self,
+        route_interface: &RouteInterface,
+        netns_file: &File,
+    ) -> Result<()> {
+        self.handle()
+            .link()
+            .set(
+                LinkUnspec::new_with_index(route_interface.index)
+                    .setns_by_fd(netns_file.as_raw_fd())
+                    .build(),
+            )
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_bridge_options(
+        &self,
+        bridge: &RouteInterface,
+        options: &BridgeOptions,
+    ) -> Result<()> {
+        let mut infos = vec![];
+        if let Some(stp_enabled) = options.stp_enabled {
+            infos.push(InfoBridge::StpState(stp_enabled.into()));
+        }
+        if let Some(forward_delay) = options.forward_delay {
+            infos.push(InfoBridge::ForwardDelay(forward_delay));
+        }
+        if let Some(vlan_filtering) = options.vlan_filtering {
+            infos.push(InfoBridge::VlanFiltering(vlan_filtering));
+        }
+
+        // Bridge options can only be changed through RTM_NEWLINK
+        self.handle()
+            .link()
+            .set_port(
+                LinkMessageBuilder::<LinkBridge>::new_with_info_kind(InfoKind::Bridge)
+                    .index(bridge.index)
+                    .set_info_data(InfoData::Bridge(infos))
+                    .build(),
+            )
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_link(&self, route_interface: &RouteInterface) -> Result<()> {
+        self.handle()
+            .link()
+            .del(route_interface.index)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
 }
 
-impl Drop for RouteManager {
-    fn drop(&mut self) {
-        self.rtnetlink_future.abort();
+// What the root qdisc of a link is used for
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RootQdisc {
+    // The one the kernel sets up, which has no handle
+    Default,
+    // HTB qdisc of address limits, which lets other traffic through as is
+    AddressLimits,
+    // A shaper of the whole link, or anything set up outside of the service
+    Other,
+}
+
+// Traffic control
+impl RouteManager {
+    pub async fn get_qdiscs(&self) -> Result<Vec<Qdisc>> {
+        let mut qdiscs = self.handle().qdisc().get().execute();
+        let mut result = vec![];
+        while let Some(qdisc) = qdiscs.try_next().await? {
+            result.push(qdisc.into());
+        }
+
+        Ok(result)
+    }
+
+    async fn find_qdisc(&self, index: u32, parent: TcHandle) -> Result<Option<TcMessage>> {
+        let mut qdiscs = self.handle().qdisc().get().execute();
+        while let Some(qdisc) = qdiscs.try_next().await? {
+            if qdisc.header.index == index as i32 && qdisc.header.parent == parent {
+                return Ok(Some(qdisc));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn get_root_qdisc(&self, index: u32) -> Result<RootQdisc> {
+        let Some(root) = self.find_qdisc(index, TcHandle::ROOT).await? else {
+            return Ok(RootQdisc::Default);
+        };
+        if root.header.handle == TcHandle::UNSPEC {
+            return Ok(RootQdisc::Default);
+        }
+
+        // HTB shapers of the whole link have a class of their own
+        let is_htb = root.header.handle == SHAPER_HANDLE
+            && root
+                .attributes
+                .iter()
+                .any(|attr| matches!(attr, TcAttribute::Kind(kind) if kind == "htb"));
+        if is_htb && !self.get_classes(index).await?.contains(&SHAPER_CLASS) {
+            return Ok(RootQdisc::AddressLimits);
+        }
+
+        Ok(RootQdisc::Other)
+    }
+
+    async fn delete_qdisc(&self, index: u32, parent: TcHandle) -> Result<()> {
+        let mut request = self.handle().qdisc().del(index as i32);
+        request.message_mut().header.parent = parent;
+        request.execute().await?;
+        Ok(())
+    }
+
+    // Creates a qdisc or class, which rtnetlink can't attach options to
+    async fn add_tc_object(&self, message: RouteNetlinkMessage) -> Result<()> {
+        self.send_tc_request(message, NLM_F_CREATE | NLM_F_EXCL)
+            .await
+    }
+
+    async fn send_tc_request(&self, message: RouteNetlinkMessage, flags: u16) -> Result<()> {
+        let mut request = NetlinkMessage::from(message);
+        request.header.flags = NLM_F_REQUEST | NLM_F_ACK | flags;
+
+        let mut responses = self.handle().request(request)?;
+        while let Some(response) = responses.next().await {
+            if let NetlinkPayload::Error(e) = response.payload
+                && e.code.is_some()
+            {
+                return Err(rtnetlink::Error::NetlinkError(e).into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn tc_message(
+        index: u32,
+        parent: TcHandle,
+        handle: TcHandle,
+        kind: &str,
+        options: Vec<TcOption>,
+    ) -> TcMessage {
+        let mut message = TcMessage::with_index(index as i32);
+        message.header.parent = parent;
+        message.header.handle = handle;
+        message.attributes.push(TcAttribute::Kind(kind.to_owned()));
+        if !options.is_empty() {
+            message.attributes.push(TcAttribute::Options(options));
+        }
+        message
+    }
+
+    // Replaces the root qdisc of the link with `shaper`, or brings back the
+    // default one if `None`. Links with address limits are left to them.
+    pub async fn set_link_shaper(&self, index: u32, shaper: Option<&TrafficShaper>) -> Result<()> {
+        if !self.get_address_classes(index).await?.is_empty() {
+            return Err(TrafficControlConflict { index }.into());
+        }
+
+        // The default root qdisc has no handle and can't be deleted
+        if let Some(qdisc) = self.find_qdisc(index, TcHandle::ROOT).await?
+            && qdisc.header.handle != TcHandle::UNSPEC
+        {
+            self.delete_qdisc(index, TcHandle::ROOT).await?;
+        }
+
+        let Some(shaper) = shaper else {
+            return Ok(());
+        };
+
+        let (kind, options) = match shaper {
+            TrafficShaper::FqCodel => ("fq_codel", vec![]),
+            TrafficShaper::Cake { rate } => ("cake", tc::cake_options(*rate)),
+            TrafficShaper::Tbf { rate } => ("tbf", tc::tbf_options(*rate)),
+            TrafficShaper::Htb { .. } => ("htb", tc::htb_options(SHAPER_CLASS.minor)),
+        };
+        self.add_tc_object(RouteNetlinkMessage::NewQueueDiscipline(Self::tc_message(
+            index,
+            TcHandle::ROOT,
+            SHAPER_HANDLE,
+            kind,
+            options,
+        )))
+        .await?;
+
+        // HTB only shapes the traffic of its classes, which are then fair
+        // queued
+        if let TrafficShaper::Htb { rate } = shaper {
+            self.add_tc_object(RouteNetlinkMessage::NewTrafficClass(Self::tc_message(
+                index,
+                SHAPER_HANDLE,
+                SHAPER_CLASS,
+                "htb",
+                tc::htb_class_options(*rate),
+            )))
+            .await?;
+            self.add_tc_object(RouteNetlinkMessage::NewQueueDiscipline(Self::tc_message(
+                index,
+                SHAPER_CLASS,
+                SHAPER_LEAF_HANDLE,
+                "fq_codel",
+                vec![],
+            )))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    // Incoming traffic can't be queued, so it is redirected to an IFB device
+    // and shaped on its way out of it
+    pub async fn set_link_ingress_shaper(
+        &self,
+        route_interface: &RouteInterface,
+        shaper: Option<&TrafficShaper>,
+    ) -> Result<()> {
+        let Some(shaper) = shaper else {
+            // The IFB device may carry address limits as well
+            if let Some(ifb) = self.find_ifb(route_interface).await?
+                && !self.get_address_classes(ifb.index).await?.is_empty()
+            {
+                return Err(TrafficControlConflict { index: ifb.index }.into());
+            }
+            return self.remove_ingress_redirect(route_interface).await;
+        };
+
+        let ifb_index = self.redirect_ingress(route_interface).await?;
+        self.set_link_shaper(ifb_index, Some(shaper)).await
+    }
+
+    async fn find_ifb(&self, route_interface: &RouteInterface) -> Result<Option<RouteInterface>> {
+        let ifb_name = tc::ifb_name(route_interface.index);
+        Ok(self
+            .get_interfaces()
+            .await?
+            .into_iter()
+            .find(|x| x.name == ifb_name))
+    }
+
+    // Redirects the incoming traffic of the link to its IFB device, creating
+    // the device if needed, and returns the index of the device
+    async fn redirect_ingress(&self, route_interface: &RouteInterface) -> Result<u32> {
+        let index = route_interface.index;
+        let ifb_name = tc::ifb_name(index);
+
+        let ifb_index = match self.find_ifb(route_interface).await? {
+            Some(ifb) => ifb.index,
+            None => {
+                self.handle()
+                    .link()
+                    .add(
+                        LinkMessageBuilder::<LinkUnspec>::new_with_info_kind(InfoKind::Ifb)
+                            .name(ifb_name.clone())
+                            .up()
+                            .build(),
+                    )
+                    .execute()
+                    .await?;
+                self.find_ifb(route_interface)
+                    .await?
+                    .ok_or(anyhow!("IFB device was not created: {}", ifb_name))?
+                    .index
+            }
+        };
+
+        // The redirect filter lives and dies with the ingress qdisc
+        if self.find_qdisc(index, TcHandle::INGRESS).await?.is_none() {
+            self.handle()
+                .qdisc()
+                .add(index as i32)
+                .ingress()
+                .execute()
+                .await?;
+            self.handle()
+                .traffic_filter(index as i32)
+                .add()
+                .parent(INGRESS_HANDLE.into())
+                .protocol(ETH_P_ALL.to_be())
+                .redirect(ifb_index)?
+                .execute()
+                .await?;
+        }
+
+        Ok(ifb_index)
+    }
+
+    async fn remove_ingress_redirect(&self, route_interface: &RouteInterface) -> Result<()> {
+        // Removing the ingress qdisc also removes the redirect filter
+        if self
+            .find_qdisc(route_interface.index, TcHandle::INGRESS)
+            .await?
+            .is_some()
+        {
+            self.delete_qdisc(route_interface.index, TcHandle::INGRESS)
+                .await?;
+        }
+
+        if let Some(ifb) = self.find_ifb(route_interface).await? {
+            self.delete_link(&ifb).await?;
+        }
+        Ok(())
+    }
+
+    // Number of address limit classes on the link, for checking that they
+    // haven't been replaced by something else
+    pub async fn count_link_address_limits(&self, index: u32) -> Result<usize> {
+        Ok(self.get_address_classes(index).await?.len())
+    }
+
+    async fn get_classes(&self, index: u32) -> Result<Vec<TcHandle>> {
+        let mut classes = self.handle().traffic_class(index as i32).get().execute();
+        let mut result = vec![];
+        while let Some(class) = classes.try_next().await? {
+            result.push(class.header.handle);
+        }
+
+        Ok(result)
+    }
+
+    async fn get_address_classes(&self, index: u32) -> Result<Vec<TcHandle>> {
+        Ok(self
+            .get_classes(index)
+            .await?
+            .into_iter()
+            .filter(|x| x.major == SHAPER_HANDLE.major && x.minor >= ADDRESS_CLASS_BASE)
+            .collect())
+    }
+
+    // Address limits need the root qdisc of the link to themselves, and that
+    // of its IFB device for incoming traffic. Checked before any of them are
+    // changed, so that they are never applied in one direction only.
+    pub async fn check_link_address_limits(
+        &self,
+        route_interface: &RouteInterface,
+        download: bool,
+        upload: bool,
+    ) -> Result<()> {
+        let index = route_interface.index;
+        if download && self.get_root_qdisc(index).await? == RootQdisc::Other {
+            return Err(TrafficControlConflict { index }.into());
+        }
+
+        if upload
+            && let Some(ifb) = self.find_ifb(route_interface).await?
+            && self.get_root_qdisc(ifb.index).await? == RootQdisc::Other
+        {
+            return Err(TrafficControlConflict { index: ifb.index }.into());
+        }
+
+        Ok(())
+    }
+
+    // Deletes the filters of one priority, which may not exist
+    async fn delete_filters(&self, index: u32, parent: TcHandle, priority: u16) -> Result<()> {
+        let mut message = TcMessage::with_index(index as i32);
+        message.header.parent = parent;
+        message.header.info = u32::from(TcHandle {
+            major: priority,
+            minor: 0,
+        });
+
+        let mut request = NetlinkMessage::from(RouteNetlinkMessage::DelTrafficFilter(message));
+        request.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        let mut responses = self.handle().request(request)?;
+        while let Some(response) = responses.next().await {
+            if let NetlinkPayload::Error(e) = response.payload
+                && e.code.is_some()
+                && e.to_io().raw_os_error() != Some(libc::ENOENT)
+            {
+                return Err(rtnetlink::Error::NetlinkError(e).into());
+            }
+        }
+
+        Ok(())
+    }
+
+    // Shapes the traffic going out of the link to, or if `source` from, each
+    // group of addresses. The limits replace the ones that were set before.
+    // Root qdiscs set up for anything else are never replaced.
+    pub async fn set_link_address_limits(
+        &self,
+        index: u32,
+        source: bool,
+        limits: &[AddressLimit],
+    ) -> Result<()> {
+        let root = self.get_root_qdisc(index).await?;
+        if root == RootQdisc::Other && !limits.is_empty() {
+            return Err(TrafficControlConflict { index }.into());
+        }
+
+        // Filters have to go before the classes they point to. Classes left
+        // next to the one of an HTB shaper are removed as well.
+        let classes = self.get_address_classes(index).await?;
+        if root == RootQdisc::AddressLimits || !classes.is_empty() {
+            for priority in [
+                tc::ADDRESS_FILTER_PRIORITY_V4,
+                tc::ADDRESS_FILTER_PRIORITY_V6,
+            ] {
+                self.delete_filters(index, SHAPER_HANDLE, priority).await?;
+            }
+            for class in classes {
+                let mut message = TcMessage::with_index(index as i32);
+                message.header.parent = SHAPER_HANDLE;
+                message.header.handle = class;
+                self.send_tc_request(RouteNetlinkMessage::DelTrafficClass(message), 0)
+                    .await?;
+            }
+        }
+
+        if limits.is_empty() {
+            if root == RootQdisc::AddressLimits {
+                self.delete_qdisc(index, TcHandle::ROOT).await?;
+            }
+            return Ok(());
+        }
+
+        if root == RootQdisc::Default {
+            self.add_tc_object(RouteNetlinkMessage::NewQueueDiscipline(Self::tc_message(
+                index,
+                TcHandle::ROOT,
+                SHAPER_HANDLE,
+                "htb",
+                tc::htb_options(0),
+            )))
+            .await?;
+        }
+
+        for (i, limit) in limits.iter().enumerate() {
+            let class = TcHandle {
+                major: SHAPER_HANDLE.major,
+                minor: ADDRESS_CLASS_BASE + i as u16,
+            };
+            self.add_tc_object(RouteNetlinkMessage::NewTrafficClass(Self::tc_message(
+                index,
+                SHAPER_HANDLE,
+                class,
+                "htb",
+                tc::htb_class_options(limit.rate),
+            )))
+            .await?;
+
+            for address in limit.addresses.iter() {
+                let (protocol, priority, selector) = tc::address_selector(*address, source);
+                self.handle()
+                    .traffic_filter(index as i32)
+                    .add()
+                    .parent(SHAPER_HANDLE.into())
+                    .priority(priority)
+                    .protocol(protocol.to_be())
+                    .u32(&[
+                        TcFilterU32Option::Selector(selector),
+                        TcFilterU32Option::ClassId(class),
+                    ])?
+                    .execute()
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Incoming traffic is limited on the IFB device, which is removed along
+    // with the limits unless it shapes the whole link instead
+    pub async fn set_link_ingress_address_limits(
+        &self,
+        route_interface: &RouteInterface,
+        limits: &[AddressLimit],
+    ) -> Result<()> {
+        if limits.is_empty() {
+            if let Some(ifb) = self.find_ifb(route_interface).await? {
+                self.set_link_address_limits(ifb.index, true, &[]).await?;
+                if self.get_root_qdisc(ifb.index).await? == RootQdisc::Default {
+                    self.remove_ingress_redirect(route_interface).await?;
+                }
+            }
+            return Ok(());
+        }
+
+        let ifb_index = self.redirect_ingress(route_interface).await?;
+        self.set_link_address_limits(ifb_index, true, limits).await
+    }
+
+    pub async fn count_link_ingress_address_limits(
+        &self,
+        route_interface: &RouteInterface,
+    ) -> Result<usize> {
+        match self.find_ifb(route_interface).await? {
+            Some(ifb) => self.count_link_address_limits(ifb.index).await,
+            None => Ok(0),
+        }
     }
 }
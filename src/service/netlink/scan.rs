@@ -0,0 +1,200 @@
+use macaddr::MacAddr;
+use serde::Serialize;
+use wl_nl80211::{Nl80211BssCapabilities, Nl80211BssInfo, Nl80211Element};
+
+use crate::service::netlink::phy::frequency_to_channel;
+
+// Suite selectors are read as little endian, so the OUI is in the low bytes
+// and the suite type in the high one
+const IEEE_80211_OUI: u32 = 0x00ac0f00;
+const WPA_OUI: [u8; 3] = [0x00, 0x50, 0xf2];
+const WPA_OUI_TYPE: u8 = 1;
+
+// AKM suite types that are only used by WPA3: SAE, FT-SAE, the Suite B
+// ones, OWE (Enhanced Open) and SAE with group dependent hashes
+const WPA3_AKM_SUITE_TYPES: [u32; 8] = [8, 9, 11, 12, 13, 18, 24, 25];
+
+// Strongest protection offered by a BSS. Transition mode networks report
+// the stronger of their two modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum BssSecurity {
+    Open,
+    Wep,
+    Wpa,
+    Wpa2,
+    Wpa3,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetlinkBss {
+    pub bssid: String,
+    // Missing for hidden networks
+    pub ssid: Option<String>,
+    // MHz
+    pub frequency: u32,
+    pub channel: Option<u32>,
+    // dBm
+    pub signal: Option<i32>,
+    // Time units of 1024µs
+    pub beacon_interval: Option<u16>,
+    // Milliseconds since the BSS was last seen
+    pub last_seen: Option<u32>,
+    pub security: BssSecurity,
+    // AKM suites offered through the RSN element, e.g. Psk or Sae
+    pub key_management: Vec<String>,
+    // The scanning interface is associated with, or has joined, this BSS
+    pub associated: bool,
+}
+
+impl NetlinkBss {
+    // BSSs missing their address or frequency are left out
+    pub fn parse(attributes: Vec<Nl80211BssInfo>) -> Option<Self> {
+        let mut bssid = None;
+        let mut frequency = None;
+        let mut signal = None;
+        let mut beacon_interval = None;
+        let mut last_seen = None;
+        let mut privacy = false;
+        let mut associated = false;
+        let mut elements = None;
+        let mut beacon_elements = None;
+
+        for attr in attributes {
+            match attr {
+                Nl80211BssInfo::Bssid(mac) => bssid = Some(MacAddr::from(mac)),
+                Nl80211BssInfo::Frequency(f) => frequency = Some(f),
+                Nl80211BssInfo::SignalMbm(mbm) => signal = Some(mbm / 100),
+                Nl80211BssInfo::BeaconInterval(i) => beacon_interval = Some(i),
+                Nl80211BssInfo::SeenMsAgo(ms) => last_seen = Some(ms),
+                Nl80211BssInfo::Capability(caps) => {
+                    privacy = caps.contains(Nl80211BssCapabilities::Privacy);
+                }
+                // Authenticated only (0), associated (1) or joined (2)
+                Nl80211BssInfo::Status(status) => associated = status != 0,
+                Nl80211BssInfo::InformationElements(e) => elements = Some(e),
+                Nl80211BssInfo::BeaconInformationElements(e) => beacon_elements = Some(e),
+                _ => {}
+            }
+        }
+
+        // The elements of the latest frame, which is a probe response for
+        // active scans, are preferred over those of the beacon
+        let elements = elements.or(beacon_elements).unwrap_or_default();
+        let frequency = frequency?;
+        let (security, key_management) = parse_security(&elements, privacy);
+
+        Some(Self {
+            bssid: bssid?.to_string(),
+            ssid: parse_ssid(&elements),
+            frequency,
+            channel: frequency_to_channel(frequency),
+            signal,
+            beacon_interval,
+            last_seen,
+            security,
+            key_management,
+            associated,
+        })
+    }
+}
+
+// Hidden networks send an empty SSID, or one made of null bytes
+fn parse_ssid(elements: &[Nl80211Element]) -> Option<String> {
+    elements.iter().find_map(|x| match x {
+        Nl80211Element::Ssid(ssid) if !ssid.chars().all(|c| c == '\0') => Some(ssid.clone()),
+        _ => None,
+    })
+}
+
+fn parse_security(elements: &[Nl80211Element], privacy: bool) -> (BssSecurity, Vec<String>) {
+    let rsn = elements.iter().find_map(|x| match x {
+        Nl80211Element::Rsn(rsn) => Some(rsn),
+        _ => None,
+    });
+    if let Some(rsn) = rsn {
+        let wpa3 = rsn.akm_suits.iter().any(|x| {
+            let selector = u32::from(*x);
+            selector & 0x00ffffff == IEEE_80211_OUI
+                && WPA3_AKM_SUITE_TYPES.contains(&(selector >> 24))
+        });
+        let key_management = rsn.akm_suits.iter().map(|x| format!("{:?}", x)).collect();
+        let security = match wpa3 {
+            true => BssSecurity::Wpa3,
+            false => BssSecurity::Wpa2,
+        };
+        return (security, key_management);
+    }
+
+    // WPA predates RSN and uses a vendor specific element instead
+    let wpa = elements.iter().any(|x| match x {
+        Nl80211Element::Vendor(data) => {
+            data.len() >= 4 && data[..3] == WPA_OUI && data[3] == WPA_OUI_TYPE
+        }
+        _ => false,
+    });
+
+    let security = match (wpa, privacy) {
+        (true, _) => BssSecurity::Wpa,
+        (false, true) => BssSecurity::Wep,
+        (false, false) => BssSecurity::Open,
+    };
+    (security, vec![])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wl_nl80211::Nl80211ElementRsn;
+
+    const AKM_PSK: u32 = IEEE_80211_OUI | 2 << 24;
+    const AKM_SAE: u32 = IEEE_80211_OUI | 8 << 24;
+
+    #[test]
+    fn transition_mode_reports_wpa3() {
+        let rsn = Nl80211Element::Rsn(Nl80211ElementRsn {
+            version: 1,
+            akm_suits: vec![AKM_PSK.into(), AKM_SAE.into()],
+            ..Default::default()
+        });
+        let (security, key_management) = parse_security(&[rsn], true);
+
+        assert_eq!(security, BssSecurity::Wpa3);
+        assert_eq!(key_management, ["Psk", "Sae"]);
+    }
+
+    #[test]
+    fn psk_only_reports_wpa2() {
+        let rsn = Nl80211Element::Rsn(Nl80211ElementRsn {
+            version: 1,
+            akm_suits: vec![AKM_PSK.into()],
+            ..Default::default()
+        });
+        let (security, key_management) = parse_security(&[rsn], true);
+
+        assert_eq!(security, BssSecurity::Wpa2);
+        assert_eq!(key_management, ["Psk"]);
+    }
+
+    #[test]
+    fn suites_of_other_vendors_are_not_wpa3() {
+        // SAE's suite type, under the OUI of the WPA vendor element
+        let rsn = Nl80211Element::Rsn(Nl80211ElementRsn {
+            version: 1,
+            akm_suits: vec![(0x00f25000 | 8 << 24).into()],
+            ..Default::default()
+        });
+
+        assert_eq!(parse_security(&[rsn], true).0, BssSecurity::Wpa2);
+    }
+
+    #[test]
+    fn security_without_rsn_element() {
+        let wpa = Nl80211Element::Vendor(vec![0x00, 0x50, 0xf2, 0x01, 0x01, 0x00]);
+
+        assert_eq!(parse_security(&[wpa], true).0, BssSecurity::Wpa);
+        assert_eq!(parse_security(&[], true).0, BssSecurity::Wep);
+        assert_eq!(parse_security(&[], false).0, BssSecurity::Open);
+    }
+}
@@ -0,0 +1,158 @@
+use std::net::IpAddr;
+
+use macaddr::MacAddr;
+use serde::Serialize;
+use wl_nl80211::{Nl80211Attr, Nl80211RateInfo, Nl80211StationInfo};
+
+// 802.11 generation a rate was sent with
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum RateStandard {
+    Legacy,
+    Ht,
+    Vht,
+    He,
+    Eht,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StationRate {
+    // kbit/s
+    pub bitrate: Option<u32>,
+    pub standard: RateStandard,
+    pub mcs: Option<u8>,
+    // Spatial streams, not reported for HT where the MCS implies them
+    pub nss: Option<u8>,
+    // MHz, 20 unless reported otherwise
+    pub width: u32,
+    pub short_gi: bool,
+}
+
+impl StationRate {
+    fn parse(info: &[Nl80211RateInfo]) -> Self {
+        let mut rate = Self {
+            bitrate: None,
+            standard: RateStandard::Legacy,
+            mcs: None,
+            nss: None,
+            width: 20,
+            short_gi: false,
+        };
+
+        for attr in info {
+            match attr {
+                // Both are in units of 100kbit/s, the 16-bit one is left
+                // out by the kernel when the rate doesn't fit in it
+                Nl80211RateInfo::Bitrate(b) if rate.bitrate.is_none() => {
+                    rate.bitrate = Some(u32::from(*b) * 100);
+                }
+                Nl80211RateInfo::Bitrate32(b) => rate.bitrate = Some(b * 100),
+                Nl80211RateInfo::Mcs(mcs) => {
+                    rate.standard = RateStandard::Ht;
+                    rate.mcs = Some(*mcs);
+                }
+                Nl80211RateInfo::VhtMcs(mcs) => {
+                    rate.standard = RateStandard::Vht;
+                    rate.mcs = Some(*mcs);
+                }
+                Nl80211RateInfo::HeMcs(mcs) => {
+                    rate.standard = RateStandard::He;
+                    rate.mcs = Some(*mcs);
+                }
+                Nl80211RateInfo::EhtMcs(mcs) => {
+                    rate.standard = RateStandard::Eht;
+                    rate.mcs = Some(*mcs);
+                }
+                Nl80211RateInfo::VhtNss(nss)
+                | Nl80211RateInfo::HeNss(nss)
+                | Nl80211RateInfo::EhtNss(nss) => rate.nss = Some(*nss),
+                Nl80211RateInfo::MhzWidth(width) => rate.width = *width,
+                Nl80211RateInfo::MhzWidth80Plus80 => rate.width = 160,
+                Nl80211RateInfo::ShortGi => rate.short_gi = true,
+                _ => {}
+            }
+        }
+
+        rate
+    }
+}
+
+// Client connected to an access point interface
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetlinkStation {
+    pub mac_address: String,
+    // Filled in by the NetlinkService from the neighbour table
+    pub ip_addresses: Vec<IpAddr>,
+    // dBm
+    pub signal: Option<i8>,
+    pub signal_avg: Option<i8>,
+    pub tx_bitrate: Option<StationRate>,
+    pub rx_bitrate: Option<StationRate>,
+    pub rx_bytes: Option<u64>,
+    pub tx_bytes: Option<u64>,
+    pub rx_packets: Option<u32>,
+    pub tx_packets: Option<u32>,
+    // Milliseconds since the station was last heard from
+    pub inactive_time: Option<u32>,
+    // Seconds since the station connected
+    pub connected_time: Option<u32>,
+}
+
+impl NetlinkStation {
+    pub fn parse(attributes: Vec<Nl80211Attr>) -> Option<Self> {
+        let mut mac = None;
+        let mut info = vec![];
+        for attr in attributes {
+            match attr {
+                Nl80211Attr::Mac(m) => mac = Some(MacAddr::from(m)),
+                Nl80211Attr::StationInfo(i) => info = i,
+                _ => {}
+            }
+        }
+
+        let mut station = Self {
+            mac_address: mac?.to_string(),
+            ip_addresses: vec![],
+            signal: None,
+            signal_avg: None,
+            tx_bitrate: None,
+            rx_bitrate: None,
+            rx_bytes: None,
+            tx_bytes: None,
+            rx_packets: None,
+            tx_packets: None,
+            inactive_time: None,
+            connected_time: None,
+        };
+
+        for attr in info {
+            match attr {
+                Nl80211StationInfo::Signal(s) => station.signal = Some(s),
+                Nl80211StationInfo::SignalAvg(s) => station.signal_avg = Some(s),
+                Nl80211StationInfo::TxBitrate(r) => {
+                    station.tx_bitrate = Some(StationRate::parse(&r))
+                }
+                Nl80211StationInfo::RxBitrate(r) => {
+                    station.rx_bitrate = Some(StationRate::parse(&r))
+                }
+                // The 32-bit counters wrap around, so the 64-bit ones win
+                Nl80211StationInfo::RxBytes(b) if station.rx_bytes.is_none() => {
+                    station.rx_bytes = Some(b.into());
+                }
+                Nl80211StationInfo::TxBytes(b) if station.tx_bytes.is_none() => {
+                    station.tx_bytes = Some(b.into());
+                }
+                Nl80211StationInfo::RxBytes64(b) => station.rx_bytes = Some(b),
+                Nl80211StationInfo::TxBytes64(b) => station.tx_bytes = Some(b),
+                Nl80211StationInfo::RxPackets(p) => station.rx_packets = Some(p),
+                Nl80211StationInfo::TxPackets(p) => station.tx_packets = Some(p),
+                Nl80211StationInfo::InactiveTime(t) => station.inactive_time = Some(t),
+                Nl80211StationInfo::ConnectedTime(t) => station.connected_time = Some(t),
+                _ => {}
+            }
+        }
+
+        Some(station)
+    }
+}
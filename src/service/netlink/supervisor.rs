@@ -0,0 +1,175 @@
+use std::{
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures_util::future::{self, BoxFuture};
+use serde::Serialize;
+use tokio::{sync::broadcast, task::JoinHandle};
+
+use crate::service::netlink::events::NetlinkEvent;
+
+// Bounds of the delay between reconnection attempts, which doubles after
+// every failed attempt
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionHealth {
+    pub connected: bool,
+    // When the connection last went up or down
+    pub since: DateTime<Utc>,
+    pub reconnects: u64,
+    // Times the kernel dropped notifications because the socket buffer was full
+    pub overruns: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct HealthMonitor {
+    health: RwLock<ConnectionHealth>,
+}
+
+impl HealthMonitor {
+    pub fn new() -> Self {
+        Self {
+            health: RwLock::new(ConnectionHealth {
+                connected: true,
+                since: Utc::now(),
+                reconnects: 0,
+                overruns: 0,
+                last_error: None,
+            }),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut ConnectionHealth)) {
+        f(&mut self.health.write().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    pub fn get(&self) -> ConnectionHealth {
+        self.health
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn overrun(&self) {
+        self.update(|health| health.overruns += 1);
+    }
+}
+
+// An open netlink connection: the handle used for requests, and the futures
+// that have to be polled for it to work. The connection is considered dead
+// as soon as any of the futures completes.
+pub struct Connection<H> {
+    pub handle: H,
+    pub futures: Vec<BoxFuture<'static, ()>>,
+}
+
+// Shared handle of a supervised connection, which gets replaced when the
+// connection is reestablished
+pub struct SupervisedHandle<H> {
+    handle: Arc<RwLock<H>>,
+    health: Arc<HealthMonitor>,
+    supervisor_future: JoinHandle<()>,
+}
+
+impl<H: Clone + Send + Sync + 'static> SupervisedHandle<H> {
+    // Supervises `connection`, using `connect` to replace it once it dies.
+    // `events` is notified of reconnections, since any notification sent in
+    // the meantime is lost.
+    pub fn new<F>(
+        name: &'static str,
+        connection: Connection<H>,
+        health: Arc<HealthMonitor>,
+        events: Option<broadcast::Sender<NetlinkEvent>>,
+        connect: F,
+    ) -> Self
+    where
+        F: Fn() -> BoxFuture<'static, Result<Connection<H>>> + Send + 'static,
+    {
+        let handle = Arc::new(RwLock::new(connection.handle));
+        let supervisor_future = tokio::spawn(Self::supervise(
+            name,
+            connection.futures,
+            handle.clone(),
+            health.clone(),
+            events,
+            connect,
+        ));
+
+        Self {
+            handle,
+            health,
+            supervisor_future,
+        }
+    }
+
+    async fn supervise<F>(
+        name: &'static str,
+        mut futures: Vec<BoxFuture<'static, ()>>,
+        handle: Arc<RwLock<H>>,
+        health: Arc<HealthMonitor>,
+        events: Option<broadcast::Sender<NetlinkEvent>>,
+        connect: F,
+    ) where
+        F: Fn() -> BoxFuture<'static, Result<Connection<H>>>,
+    {
+        loop {
+            future::select_all(futures).await;
+            log::warn!("{} connection has ended, reconnecting...", name);
+            health.update(|health| {
+                health.connected = false;
+                health.since = Utc::now();
+            });
+
+            let mut delay = MIN_RECONNECT_DELAY;
+            let connection = loop {
+                match connect().await {
+                    Ok(connection) => break connection,
+                    Err(e) => {
+                        log::error!("Failed to reconnect {} connection: {}", name, e);
+                        health.update(|health| health.last_error = Some(e.to_string()));
+                    }
+                }
+
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            };
+
+            *handle.write().unwrap_or_else(PoisonError::into_inner) = connection.handle;
+            futures = connection.futures;
+            health.update(|health| {
+                health.connected = true;
+                health.since = Utc::now();
+                health.reconnects += 1;
+            });
+            log::info!("{} connection has been reestablished", name);
+
+            if let Some(events) = &events {
+                let _ = events.send(NetlinkEvent::Reconnected);
+            }
+        }
+    }
+
+    pub fn get(&self) -> H {
+        self.handle
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn health(&self) -> ConnectionHealth {
+        self.health.get()
+    }
+}
+
+impl<H> Drop for SupervisedHandle<H> {
+    fn drop(&mut self) {
+        self.supervisor_future.abort();
+    }
+}
@@ -0,0 +1,257 @@
+use rtnetlink::packet_core::Nla;
+use serde::Serialize;
+use wl_nl80211::{Nl80211Attr, Nl80211SurveyInfo};
+
+use crate::service::netlink::{
+    NetlinkBss, NetlinkPhy,
+    phy::{PhyBandKind, frequency_to_channel},
+};
+
+const NL80211_SURVEY_INFO_IN_USE: u16 = 3;
+
+// 2.4 GHz channels are 5 MHz apart but about 22 MHz wide, so networks on
+// channels closer than this interfere with each other
+const OVERLAP_2GHZ: u32 = 25;
+// Signal of a neighbouring network that counts fully against a channel,
+// weaker ones count less, down to a tenth at the noise floor
+const STRONG_SIGNAL: i32 = -50;
+const NOISE_FLOOR: i32 = -95;
+
+// Channel statistics gathered by the device, mostly while scanning.
+// Times are in milliseconds.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelSurvey {
+    pub frequency: u32,
+    // dBm
+    pub noise: Option<i8>,
+    // Time spent on the channel
+    pub active_time: Option<u64>,
+    // Time the medium was sensed busy
+    pub busy_time: Option<u64>,
+    pub rx_time: Option<u64>,
+    pub tx_time: Option<u64>,
+    // The device currently operates on this channel
+    pub in_use: bool,
+}
+
+impl ChannelSurvey {
+    pub fn parse(attributes: Vec<Nl80211Attr>) -> Option<Self> {
+        let info = attributes.into_iter().find_map(|x| match x {
+            Nl80211Attr::SurveyInfo(info) => Some(info),
+            _ => None,
+        })?;
+
+        let mut frequency = None;
+        let mut survey = Self {
+            frequency: 0,
+            noise: None,
+            active_time: None,
+            busy_time: None,
+            rx_time: None,
+            tx_time: None,
+            in_use: false,
+        };
+        for attr in info {
+            match attr {
+                Nl80211SurveyInfo::Frequency(f) => frequency = Some(f),
+                Nl80211SurveyInfo::Noise(n) => survey.noise = Some(n),
+                Nl80211SurveyInfo::ActiveTime(t) => survey.active_time = Some(t),
+                Nl80211SurveyInfo::BusyTime(t) => survey.busy_time = Some(t),
+                // wl-nl80211 parses the RX and TX times into each other
+                Nl80211SurveyInfo::TimeTx(t) => survey.rx_time = Some(t),
+                Nl80211SurveyInfo::TimeRx(t) => survey.tx_time = Some(t),
+                Nl80211SurveyInfo::Other(nla) => {
+                    survey.in_use |= nla.kind() == NL80211_SURVEY_INFO_IN_USE;
+                }
+                _ => {}
+            }
+        }
+
+        survey.frequency = frequency?;
+        Some(survey)
+    }
+
+    // Share of the time on the channel that the medium was busy, not
+    // counting what the device sent itself
+    fn utilization(&self) -> Option<f64> {
+        match (self.active_time, self.busy_time) {
+            (Some(active), Some(busy)) if active > 0 => {
+                let busy = busy.saturating_sub(self.tx_time.unwrap_or(0));
+                Some((busy as f64 / active as f64).min(1.0))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelScore {
+    pub frequency: u32,
+    pub channel: Option<u32>,
+    // Lower is better
+    pub score: f64,
+    // Share of the time the channel was busy, between 0 and 1
+    pub utilization: Option<f64>,
+    // dBm
+    pub noise: Option<i8>,
+    // Networks on the channel, or overlapping it
+    pub networks: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelRecommendation {
+    pub band: PhyBandKind,
+    // Missing if no channel of the band can be used
+    pub recommended: Option<ChannelScore>,
+    // Sorted from best to worst
+    pub channels: Vec<ChannelScore>,
+}
+
+// Scores every 20 MHz channel of the device on which it may start a network
+// by how busy the medium was, how many networks are nearby and how strong
+// they are, and how noisy it is
+pub fn recommend_channels(
+    phy: &NetlinkPhy,
+    surveys: &[ChannelSurvey],
+    networks: &[NetlinkBss],
+) -> Vec<ChannelRecommendation> {
+    phy.bands
+        .iter()
+        .map(|band| {
+            let mut channels = band
+                .frequencies
+                .iter()
+                .filter(|x| !x.disabled && !x.no_ir && !x.radar)
+                .map(|x| score_channel(band.band, x.frequency, surveys, networks))
+                .collect::<Vec<_>>();
+            channels.sort_by(|a, b| a.score.total_cmp(&b.score));
+
+            ChannelRecommendation {
+                band: band.band,
+                recommended: channels.first().cloned(),
+                channels,
+            }
+        })
+        .collect()
+}
+
+fn score_channel(
+    band: PhyBandKind,
+    frequency: u32,
+    surveys: &[ChannelSurvey],
+    networks: &[NetlinkBss],
+) -> ChannelScore {
+    let survey = surveys.iter().find(|x| x.frequency == frequency);
+    let utilization = survey.and_then(|x| x.utilization());
+    let noise = survey.and_then(|x| x.noise);
+
+    let mut interference = 0.0;
+    let mut count = 0;
+    for network in networks {
+        let overlap = match band {
+            PhyBandKind::Band2GHz => {
+                1.0 - f64::from(network.frequency.abs_diff(frequency)) / f64::from(OVERLAP_2GHZ)
+            }
+            _ if network.frequency == frequency => 1.0,
+            _ => 0.0,
+        };
+        if overlap <= 0.0 {
+            continue;
+        }
+
+        let signal = network.signal.unwrap_or(NOISE_FLOOR);
+        let strength = f64::from(signal.clamp(NOISE_FLOOR, STRONG_SIGNAL) - NOISE_FLOOR)
+            / f64::from(STRONG_SIGNAL - NOISE_FLOOR);
+        interference += overlap * (0.1 + 0.9 * strength);
+        count += 1;
+    }
+
+    // Channels without survey data are assumed to be half busy
+    let score = utilization.unwrap_or(0.5) * 100.0
+        + interference * 10.0
+        + noise.map_or(0.0, |x| f64::from((i32::from(x) - NOISE_FLOOR).max(0)));
+
+    ChannelScore {
+        frequency,
+        channel: frequency_to_channel(frequency),
+        score,
+        utilization,
+        noise,
+        networks: count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::service::netlink::scan::BssSecurity;
+
+    #[test]
+    fn neighbouring_networks_count_by_overlap_and_signal() {
+        let strong = NetlinkBss {
+            bssid: "00:11:22:33:44:55".to_owned(),
+            ssid: Some("neighbour".to_owned()),
+            frequency: 2437,
+            channel: Some(6),
+            signal: Some(STRONG_SIGNAL),
+            beacon_interval: Some(100),
+            last_seen: Some(0),
+            security: BssSecurity::Wpa2,
+            key_management: vec![],
+            associated: false,
+        };
+        let weak = NetlinkBss {
+            signal: Some(-100),
+            ..strong.clone()
+        };
+        let networks = [strong.clone()];
+        let score = |frequency| score_channel(PhyBandKind::Band2GHz, frequency, &[], &networks);
+
+        // Channels 1 and 6 don't overlap, 4 partly does
+        let (channel1, channel4, channel6) = (score(2412), score(2427), score(2437));
+        assert_eq!(
+            (channel1.networks, channel4.networks, channel6.networks),
+            (0, 1, 1)
+        );
+        assert_eq!(channel1.score, 50.0);
+        assert!((channel4.score - 56.0).abs() < 1e-9);
+        assert_eq!(channel6.score, 60.0);
+
+        // Weak networks count a tenth of strong ones
+        let weak = score_channel(PhyBandKind::Band2GHz, 2437, &[], &[weak]);
+        assert!((weak.score - 51.0).abs() < 1e-9);
+
+        // 5 GHz channels don't overlap at all
+        let networks = [NetlinkBss {
+            frequency: 5180,
+            channel: Some(36),
+            ..strong
+        }];
+        let channel36 = score_channel(PhyBandKind::Band5GHz, 5180, &[], &networks);
+        let channel40 = score_channel(PhyBandKind::Band5GHz, 5200, &[], &networks);
+        assert_eq!((channel36.networks, channel40.networks), (1, 0));
+        assert_eq!(channel36.score, 60.0);
+        assert_eq!(channel40.score, 50.0);
+    }
+
+    #[test]
+    fn own_traffic_is_not_counted_as_busy() {
+        let surveys = [ChannelSurvey {
+            frequency: 2412,
+            noise: None,
+            active_time: Some(1000),
+            busy_time: Some(600),
+            rx_time: None,
+            tx_time: Some(200),
+            in_use: false,
+        }];
+        let score = score_channel(PhyBandKind::Band2GHz, 2412, &surveys, &[]);
+
+        assert_eq!(score.utilization, Some(0.4));
+        assert!((score.score - 40.0).abs() < 1e-9);
+    }
+}
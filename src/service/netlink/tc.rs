@@ -0,0 +1,367 @@
+use std::{fmt, net::IpAddr};
+
+use rtnetlink::{
+    packet_core::DefaultNla,
+    packet_route::tc::{
+        TcAttribute, TcHandle, TcMessage, TcOption, TcU32Key, TcU32Selector, TcU32SelectorFlags,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+// Options of the qdiscs that netlink-packet-route doesn't know about yet
+const TCA_TBF_PARMS: u16 = 1;
+const TCA_TBF_RATE64: u16 = 4;
+const TCA_TBF_BURST: u16 = 6;
+const TCA_HTB_PARMS: u16 = 1;
+const TCA_HTB_INIT: u16 = 2;
+const TCA_HTB_RATE64: u16 = 6;
+const TCA_HTB_CEIL64: u16 = 7;
+const TCA_CAKE_BASE_RATE64: u16 = 2;
+
+const TC_LINKLAYER_ETHERNET: u8 = 1;
+const HTB_VERSION: u32 = 3;
+const HTB_RATE2QUANTUM: u32 = 10;
+
+// Size of a full Ethernet frame, which rate limiters have to let through
+// in one go
+const MAX_FRAME_SIZE: u64 = 1514;
+// Queued data is allowed to delay packets by this much before it's dropped
+const TBF_LATENCY_MS: u64 = 50;
+// Kernel scheduler ticks, as used by HTB buffers, are 64 ns long
+const PSCHED_TICK_NS: u64 = 64;
+
+// Handles of the qdiscs and classes this service sets up
+pub const SHAPER_HANDLE: TcHandle = TcHandle { major: 1, minor: 0 };
+pub const SHAPER_CLASS: TcHandle = TcHandle { major: 1, minor: 1 };
+pub const SHAPER_LEAF_HANDLE: TcHandle = TcHandle {
+    major: 10,
+    minor: 0,
+};
+pub const INGRESS_HANDLE: TcHandle = TcHandle {
+    major: 0xffff,
+    minor: 0,
+};
+pub const ETH_P_ALL: u16 = 0x0003;
+const ETH_P_IP: u16 = 0x0800;
+const ETH_P_IPV6: u16 = 0x86dd;
+
+// Classes of address limits are numbered from here, so that they never
+// collide with the class of an interface-wide shaper
+pub const ADDRESS_CLASS_BASE: u16 = 0x100;
+pub const ADDRESS_FILTER_PRIORITY_V4: u16 = 10;
+pub const ADDRESS_FILTER_PRIORITY_V6: u16 = 11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TrafficDirection {
+    Egress,
+    Ingress,
+}
+
+// Queueing setups that can be applied to an interface. Rates are in kbit/s.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all_fields = "camelCase")]
+pub enum TrafficShaper {
+    // Fair queueing without a rate limit
+    FqCodel,
+    // Fair queueing that is also able to limit the rate
+    Cake { rate: Option<u64> },
+    // Plain token bucket rate limit
+    Tbf { rate: u64 },
+    // Rate limited class with fair queueing inside of it
+    Htb { rate: u64 },
+}
+
+impl TrafficShaper {
+    pub fn rate(&self) -> Option<u64> {
+        match self {
+            Self::FqCodel => None,
+            Self::Cake { rate } => *rate,
+            Self::Tbf { rate } | Self::Htb { rate } => Some(*rate),
+        }
+    }
+}
+
+// The root qdisc of a link is set up for something else than what was
+// requested, and is left alone
+#[derive(Debug)]
+pub struct TrafficControlConflict {
+    pub index: u32,
+}
+
+impl fmt::Display for TrafficControlConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Queueing on link {} is set up for something else",
+            self.index
+        )
+    }
+}
+
+impl std::error::Error for TrafficControlConflict {}
+
+// Rate limit shared by the traffic of a group of addresses, in kbit/s
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressLimit {
+    pub addresses: Vec<IpAddr>,
+    pub rate: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QdiscStats {
+    pub bytes: u64,
+    pub packets: u32,
+    pub drops: u32,
+    pub overlimits: u32,
+    pub qlen: u32,
+    pub backlog: u32,
+}
+
+// Qdisc as listed by `tc qdisc show`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Qdisc {
+    #[serde(skip)]
+    pub index: u32,
+    // Filled in by the NetlinkService, which knows the interface names
+    pub interface_name: Option<String>,
+    pub kind: String,
+    pub handle: String,
+    pub parent: String,
+    pub stats: QdiscStats,
+}
+
+impl From<TcMessage> for Qdisc {
+    fn from(message: TcMessage) -> Self {
+        let mut kind = String::new();
+        let mut stats = QdiscStats::default();
+        for attr in message.attributes {
+            match attr {
+                TcAttribute::Kind(k) => kind = k,
+                TcAttribute::Stats(s) => {
+                    stats = QdiscStats {
+                        bytes: s.bytes,
+                        packets: s.packets,
+                        drops: s.drops,
+                        overlimits: s.overlimits,
+                        qlen: s.qlen,
+                        backlog: s.backlog,
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            index: message.header.index as u32,
+            interface_name: None,
+            kind,
+            handle: format_handle(message.header.handle),
+            parent: format_handle(message.header.parent),
+            stats,
+        }
+    }
+}
+
+// Formats handles the way `tc` prints them
+fn format_handle(handle: TcHandle) -> String {
+    match handle {
+        TcHandle::ROOT => "root".to_owned(),
+        TcHandle::INGRESS => "ingress".to_owned(),
+        TcHandle::UNSPEC => "none".to_owned(),
+        TcHandle { major, minor: 0 } => format!("{:x}:", major),
+        TcHandle { major, minor } => format!("{:x}:{:x}", major, minor),
+    }
+}
+
+fn bytes_per_second(rate_kbit: u64) -> u64 {
+    rate_kbit.saturating_mul(1000) / 8
+}
+
+// At least 10 ms worth of traffic, so that timer granularity doesn't cap
+// the rate
+fn burst_size(rate: u64) -> u64 {
+    (rate / 100).max(4 * MAX_FRAME_SIZE)
+}
+
+// `struct tc_ratespec`, rates that don't fit are sent separately as 64 bit
+fn ratespec(rate: u64) -> Vec<u8> {
+    let mut bytes = vec![0, TC_LINKLAYER_ETHERNET]; // cell_log, linklayer
+    bytes.extend_from_slice(&0u16.to_ne_bytes()); // overhead
+    bytes.extend_from_slice(&0i16.to_ne_bytes()); // cell_align
+    bytes.extend_from_slice(&0u16.to_ne_bytes()); // mpu
+    bytes.extend_from_slice(&(rate.min(u32::MAX.into()) as u32).to_ne_bytes());
+    bytes
+}
+
+fn option(kind: u16, value: Vec<u8>) -> TcOption {
+    TcOption::Other(DefaultNla::new(kind, value))
+}
+
+pub fn cake_options(rate_kbit: Option<u64>) -> Vec<TcOption> {
+    // A base rate of 0 leaves cake unlimited
+    let rate = rate_kbit.map(bytes_per_second).unwrap_or(0);
+    vec![option(TCA_CAKE_BASE_RATE64, rate.to_ne_bytes().to_vec())]
+}
+
+pub fn tbf_options(rate_kbit: u64) -> Vec<TcOption> {
+    let rate = bytes_per_second(rate_kbit);
+    let burst = burst_size(rate);
+    let limit = rate * TBF_LATENCY_MS / 1000 + burst;
+
+    // `struct tc_tbf_qopt`, the buffer is derived from TCA_TBF_BURST
+    let mut parms = ratespec(rate);
+    parms.extend(ratespec(0)); // peakrate
+    parms.extend_from_slice(&(limit.min(u32::MAX.into()) as u32).to_ne_bytes());
+    parms.extend_from_slice(&0u32.to_ne_bytes()); // buffer
+    parms.extend_from_slice(&0u32.to_ne_bytes()); // mtu
+
+    let mut options = vec![
+        option(TCA_TBF_PARMS, parms),
+        option(
+            TCA_TBF_BURST,
+            (burst.min(u32::MAX.into()) as u32).to_ne_bytes().to_vec(),
+        ),
+    ];
+    if rate > u32::MAX.into() {
+        options.push(option(TCA_TBF_RATE64, rate.to_ne_bytes().to_vec()));
+    }
+    options
+}
+
+// Unclassified traffic goes to `default_class`, or isn't shaped at all if
+// it is 0
+pub fn htb_options(default_class: u16) -> Vec<TcOption> {
+    // `struct tc_htb_glob`
+    let mut init = vec![];
+    init.extend_from_slice(&HTB_VERSION.to_ne_bytes());
+    init.extend_from_slice(&HTB_RATE2QUANTUM.to_ne_bytes());
+    init.extend_from_slice(&u32::from(default_class).to_ne_bytes()); // defcls
+    init.extend_from_slice(&0u32.to_ne_bytes()); // debug
+    init.extend_from_slice(&0u32.to_ne_bytes()); // direct_pkts
+
+    vec![option(TCA_HTB_INIT, init)]
+}
+
+pub fn htb_class_options(rate_kbit: u64) -> Vec<TcOption> {
+    let rate = bytes_per_second(rate_kbit);
+    let buffer = (burst_size(rate) * 1_000_000_000 / rate.max(1) / PSCHED_TICK_NS)
+        .min(u32::MAX.into()) as u32;
+    let quantum = (rate / u64::from(HTB_RATE2QUANTUM)).clamp(MAX_FRAME_SIZE, 200_000) as u32;
+
+    // `struct tc_htb_opt`, the class can't borrow above its own rate
+    let mut parms = ratespec(rate);
+    parms.extend(ratespec(rate)); // ceil
+    parms.extend_from_slice(&buffer.to_ne_bytes());
+    parms.extend_from_slice(&buffer.to_ne_bytes()); // cbuffer
+    parms.extend_from_slice(&quantum.to_ne_bytes());
+    parms.extend_from_slice(&0u32.to_ne_bytes()); // level
+    parms.extend_from_slice(&0u32.to_ne_bytes()); // prio
+
+    let mut options = vec![option(TCA_HTB_PARMS, parms)];
+    if rate > u32::MAX.into() {
+        options.push(option(TCA_HTB_RATE64, rate.to_ne_bytes().to_vec()));
+        options.push(option(TCA_HTB_CEIL64, rate.to_ne_bytes().to_vec()));
+    }
+    options
+}
+
+// Name of the IFB device that the ingress traffic of a link is shaped on
+pub fn ifb_name(index: u32) -> String {
+    format!("ifb-{}", index)
+}
+
+// u32 selector matching the source or destination address of IP packets,
+// along with the protocol and priority of the filter it belongs to
+pub fn address_selector(address: IpAddr, source: bool) -> (u16, u16, TcU32Selector) {
+    // Offsets of the addresses within the IP headers
+    let (protocol, priority, octets, offset) = match address {
+        IpAddr::V4(ip) => (
+            ETH_P_IP,
+            ADDRESS_FILTER_PRIORITY_V4,
+            ip.octets().to_vec(),
+            if source { 12 } else { 16 },
+        ),
+        IpAddr::V6(ip) => (
+            ETH_P_IPV6,
+            ADDRESS_FILTER_PRIORITY_V6,
+            ip.octets().to_vec(),
+            if source { 8 } else { 24 },
+        ),
+    };
+
+    // Keys are compared against the packet as is, in network byte order
+    let keys = octets
+        .chunks_exact(4)
+        .enumerate()
+        .map(|(i, word)| {
+            let mut key = TcU32Key::default();
+            key.mask = u32::MAX;
+            key.val = u32::from_ne_bytes([word[0], word[1], word[2], word[3]]);
+            key.off = offset + 4 * i as i32;
+            key
+        })
+        .collect::<Vec<_>>();
+
+    let mut selector = TcU32Selector::default();
+    selector.flags = TcU32SelectorFlags::Terminal;
+    selector.nkeys = keys.len() as u8;
+    selector.keys = keys;
+    (protocol, priority, selector)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    #[test]
+    fn ipv4_selector_matches_the_address() {
+        let address = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+
+        let (protocol, priority, selector) = address_selector(address, true);
+        let keys = selector
+            .keys
+            .iter()
+            .map(|x| (x.off, x.mask, x.val.to_ne_bytes()))
+            .collect::<Vec<_>>();
+        assert_eq!(protocol, ETH_P_IP);
+        assert_eq!(priority, ADDRESS_FILTER_PRIORITY_V4);
+        assert_eq!(selector.flags, TcU32SelectorFlags::Terminal);
+        assert_eq!(selector.nkeys, 1);
+        assert_eq!(keys, [(12, u32::MAX, [192, 168, 1, 20])]);
+
+        let (_, _, selector) = address_selector(address, false);
+        assert_eq!(selector.keys[0].off, 16);
+    }
+
+    #[test]
+    fn ipv6_selector_matches_the_address() {
+        let address = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x20));
+
+        let (protocol, priority, selector) = address_selector(address, true);
+        let keys = selector
+            .keys
+            .iter()
+            .map(|x| (x.off, x.mask, x.val.to_ne_bytes()))
+            .collect::<Vec<_>>();
+        assert_eq!(protocol, ETH_P_IPV6);
+        assert_eq!(priority, ADDRESS_FILTER_PRIORITY_V6);
+        assert_eq!(selector.nkeys, 4);
+        assert_eq!(
+            keys,
+            [
+                (8, u32::MAX, [0x20, 0x01, 0x0d, 0xb8]),
+                (12, u32::MAX, [0, 0, 0, 0]),
+                (16, u32::MAX, [0, 0, 0, 0]),
+                (20, u32::MAX, [0, 0, 0, 0x20]),
+            ]
+        );
+
+        let (_, _, selector) = address_selector(address, false);
+        let offsets = selector.keys.iter().map(|x| x.off).collect::<Vec<_>>();
+        assert_eq!(offsets, [24, 28, 32, 36]);
+    }
+}
@@ -0,0 +1,63 @@
+use std::{fmt, future::Future};
+
+use anyhow::Result;
+use chrono::Duration;
+
+// Deadlines of netlink operations. Some drivers (USB Wi-Fi dongles in
+// particular) can take a long time to switch modes, or get stuck entirely.
+#[derive(Debug, Clone, Copy)]
+pub struct NetlinkTimeouts {
+    // Dumps and lookups
+    pub query: Duration,
+    // Creating, deleting and configuring links
+    pub link_change: Duration,
+    // Changing the mode or settings of wireless interfaces
+    pub wireless_change: Duration,
+    // Scanning for wireless networks, which hops through every channel
+    pub scan: Duration,
+}
+
+impl Default for NetlinkTimeouts {
+    fn default() -> Self {
+        Self {
+            query: Duration::seconds(5),
+            link_change: Duration::seconds(10),
+            wireless_change: Duration::seconds(30),
+            scan: Duration::seconds(20),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NetlinkTimeout {
+    pub operation: &'static str,
+    pub deadline: Duration,
+}
+
+impl fmt::Display for NetlinkTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Netlink operation '{}' did not complete within {}ms",
+            self.operation,
+            self.deadline.num_milliseconds()
+        )
+    }
+}
+
+impl std::error::Error for NetlinkTimeout {}
+
+// Fails with `NetlinkTimeout` if `future` does not complete before
+// `deadline`, in which case it is dropped
+pub async fn with_deadline<T>(
+    operation: &'static str,
+    deadline: Duration,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::time::timeout(deadline.to_std()?, future)
+        .await
+        .map_err(|_| NetlinkTimeout {
+            operation,
+            deadline,
+        })?
+}
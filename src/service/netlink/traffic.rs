@@ -0,0 +1,231 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+use crate::service::netlink::{
+    route::{LinkStats, RouteInterface, RouteManager},
+    supervisor::ConnectionHealth,
+    timeout::with_deadline,
+};
+
+// How far back the traffic history of each interface goes
+const HISTORY_DURATION: Duration = Duration::hours(1);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficSample {
+    pub timestamp: DateTime<Utc>,
+    pub stats: LinkStats,
+}
+
+// Throughput between two samples, in units per second
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficRate {
+    pub timestamp: DateTime<Utc>,
+    pub rx_bytes: f64,
+    pub tx_bytes: f64,
+    pub rx_packets: f64,
+    pub tx_packets: f64,
+    pub rx_errors: f64,
+    pub tx_errors: f64,
+    pub rx_dropped: f64,
+    pub tx_dropped: f64,
+}
+
+impl TrafficRate {
+    // Samples taken at the same time, or out of order after a clock change,
+    // have no rate
+    fn between(from: &TrafficSample, to: &TrafficSample) -> Option<Self> {
+        let milliseconds = (to.timestamp - from.timestamp).num_milliseconds();
+        if milliseconds <= 0 {
+            return None;
+        }
+        let seconds = milliseconds as f64 / 1000.0;
+        // Counters may be reset (e.g. driver reload), never report negative rates
+        let rate = |from: u64, to: u64| to.saturating_sub(from) as f64 / seconds;
+
+        Some(Self {
+            timestamp: to.timestamp,
+            rx_bytes: rate(from.stats.rx_bytes, to.stats.rx_bytes),
+            tx_bytes: rate(from.stats.tx_bytes, to.stats.tx_bytes),
+            rx_packets: rate(from.stats.rx_packets, to.stats.rx_packets),
+            tx_packets: rate(from.stats.tx_packets, to.stats.tx_packets),
+            rx_errors: rate(from.stats.rx_errors, to.stats.rx_errors),
+            tx_errors: rate(from.stats.tx_errors, to.stats.tx_errors),
+            rx_dropped: rate(from.stats.rx_dropped, to.stats.rx_dropped),
+            tx_dropped: rate(from.stats.tx_dropped, to.stats.tx_dropped),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum TrafficResolution {
+    Second,
+    Minute,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterfaceTraffic {
+    pub current: TrafficSample,
+    pub resolution: TrafficResolution,
+    pub history: Vec<TrafficRate>,
+}
+
+type TrafficHistories = Arc<RwLock<HashMap<u32, VecDeque<TrafficSample>>>>;
+
+pub struct TrafficSampler {
+    route_mgr: Arc<RouteManager>,
+    histories: TrafficHistories,
+    sampler_future: JoinHandle<()>,
+}
+
+impl TrafficSampler {
+    pub fn try_new(netns: Option<&str>, interval: Duration, timeout: Duration) -> Result<Self> {
+        let route_mgr = Arc::new(RouteManager::try_new(netns)?);
+        let capacity =
+            (HISTORY_DURATION.num_milliseconds() / interval.num_milliseconds().max(1)) as usize + 1;
+        let histories = TrafficHistories::default();
+        let sampler_future = tokio::spawn(Self::run(
+            route_mgr.clone(),
+            histories.clone(),
+            interval.to_std()?,
+            timeout,
+            capacity,
+        ));
+
+        Ok(Self {
+            route_mgr,
+            histories,
+            sampler_future,
+        })
+    }
+
+    async fn run(
+        route_mgr: Arc<RouteManager>,
+        histories: TrafficHistories,
+        interval: std::time::Duration,
+        timeout: Duration,
+        capacity: usize,
+    ) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            let interfaces =
+                match with_deadline("get links", timeout, route_mgr.get_interfaces()).await {
+                    Ok(interfaces) => interfaces,
+                    Err(e) => {
+                        log::error!("Failed to sample interface traffic: {}", e);
+                        continue;
+                    }
+                };
+
+            Self::record(&histories, interfaces, capacity);
+        }
+    }
+
+    fn record(histories: &TrafficHistories, interfaces: Vec<RouteInterface>, capacity: usize) {
+        let timestamp = Utc::now();
+        let Ok(mut histories) = histories.write() else {
+            log::error!("Failed to acquire write lock for traffic histories");
+            return;
+        };
+
+        // Forget interfaces that no longer exist
+        histories.retain(|index, _| interfaces.iter().any(|x| x.index == *index));
+
+        for iface in interfaces {
+            let Some(stats) = iface.stats else {
+                log::trace!("Interface '{}' has no statistics, skipping...", iface.name);
+                continue;
+            };
+
+            let samples = histories
+                .entry(iface.index)
+                .or_insert_with(|| VecDeque::with_capacity(capacity));
+            if samples.len() >= capacity {
+                samples.pop_front();
+            }
+            samples.push_back(TrafficSample { timestamp, stats });
+        }
+    }
+
+    pub fn health(&self) -> ConnectionHealth {
+        self.route_mgr.health()
+    }
+
+    pub fn get_latest_stats(&self, index: u32) -> Option<LinkStats> {
+        let histories = self.histories.read().ok()?;
+        histories
+            .get(&index)
+            .and_then(|samples| samples.back())
+            .map(|sample| sample.stats.clone())
+    }
+
+    pub fn get_interface_traffic(
+        &self,
+        index: u32,
+        resolution: TrafficResolution,
+    ) -> Result<InterfaceTraffic> {
+        let histories = self
+            .histories
+            .read()
+            .map_err(|_| anyhow!("Failed to acquire read lock for traffic histories"))?;
+        let samples = histories
+            .get(&index)
+            .ok_or(anyhow!("No traffic samples for interface index: {}", index))?;
+        let current = samples
+            .back()
+            .cloned()
+            .ok_or(anyhow!("No traffic samples for interface index: {}", index))?;
+
+        let mut history = vec![];
+        let mut samples = samples.iter();
+        if let Some(mut anchor) = samples.next() {
+            for sample in samples {
+                // Per-second history uses every sample, per-minute history
+                // only the first sample of every minute on the clock. Rates
+                // are computed from the actual time between the samples, so
+                // late ticks do not skew them.
+                if let TrafficResolution::Minute = resolution
+                    && Self::minute(sample) == Self::minute(anchor)
+                {
+                    continue;
+                }
+
+                // The anchor is kept, so that the next pair spans the skipped one
+                let Some(rate) = TrafficRate::between(anchor, sample) else {
+                    continue;
+                };
+                history.push(rate);
+                anchor = sample;
+            }
+        }
+
+        Ok(InterfaceTraffic {
+            current,
+            resolution,
+            history,
+        })
+    }
+
+    fn minute(sample: &TrafficSample) -> Option<DateTime<Utc>> {
+        sample.timestamp.duration_trunc(Duration::minutes(1)).ok()
+    }
+}
+
+impl Drop for TrafficSampler {
+    fn drop(&mut self) {
+        self.sampler_future.abort();
+    }
+}
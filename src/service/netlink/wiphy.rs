@@ -1,12 +1,40 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
 
-use anyhow::Result;
-use futures_util::TryStreamExt;
-use tokio::task::JoinHandle;
+use anyhow::{Result, anyhow};
+use futures_util::{StreamExt, TryStreamExt};
+use macaddr::MacAddr;
+use netlink_packet_generic::{
+    GenlFamily, GenlMessage,
+    ctrl::{
+        GenlCtrl, GenlCtrlCmd,
+        nlas::{GenlCtrlAttrs, McastGrpAttrs},
+    },
+};
+use nix::libc;
+use rtnetlink::{
+    packet_core::{DefaultNla, NLM_F_ACK, NLM_F_REQUEST, NetlinkMessage, NetlinkPayload},
+    sys::AsyncSocket,
+};
+use tokio::sync::broadcast;
 use wl_nl80211::{
-    Nl80211Attr, Nl80211IfMode, Nl80211Interface, Nl80211InterfaceType, Nl80211NewInterface,
+    Nl80211Attr, Nl80211Band, Nl80211Channel, Nl80211CipherSuit, Nl80211Error, Nl80211IfMode,
+    Nl80211Interface, Nl80211InterfaceType, Nl80211NewInterface, Nl80211Scan, Nl80211ScanFlags,
+    Nl80211Survey,
+};
+use wl_nl80211::{Nl80211Command, Nl80211Handle, Nl80211Message};
+
+use crate::service::netlink::{
+    NetlinkChannel, NetlinkChannelWidth, NetlinkInterfaceCombination, NetlinkInterfaceMode,
+    NetlinkTxPower,
+    events::NetlinkEvent,
+    netns::enter_namespace,
+    phy::frequency_to_channel,
+    regulatory::{NL80211_ATTR_REG_ALPHA2, NetlinkRegulatoryDomain},
+    scan::NetlinkBss,
+    station::NetlinkStation,
+    supervisor::{Connection, ConnectionHealth, HealthMonitor, SupervisedHandle},
+    survey::ChannelSurvey,
 };
-use wl_nl80211::{Nl80211Handle, Nl80211Message};
 
 #[derive(Debug, Clone)]
 pub struct WiphyInterface {
@@ -14,6 +42,10 @@ pub struct WiphyInterface {
     pub phy_index: u32,
     pub name: String,
     pub iftype: Nl80211InterfaceType,
+    pub use_4addr: bool,
+    pub channel: Option<NetlinkChannel>,
+    // mBm
+    pub tx_power: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -21,71 +53,421 @@ pub struct WiphyDevice {
     pub phy_index: u32,
     pub phy_name: String,
     pub supported_iftypes: Vec<Nl80211IfMode>,
+    pub software_iftypes: Vec<Nl80211InterfaceType>,
+    pub interface_combinations: Vec<NetlinkInterfaceCombination>,
+    pub bands: Vec<Nl80211Band>,
+    pub cipher_suites: Vec<Nl80211CipherSuit>,
+}
+
+impl WiphyDevice {
+    pub fn supported_modes(&self) -> Vec<NetlinkInterfaceMode> {
+        self.supported_iftypes.iter().map(|x| (*x).into()).collect()
+    }
+
+    pub fn software_modes(&self) -> Vec<NetlinkInterfaceMode> {
+        self.software_iftypes.iter().map(|x| (*x).into()).collect()
+    }
+
+    // Checks that an interface in `mode` can run next to interfaces in
+    // `others`, the way mac80211 does when it is brought up
+    pub fn check_interface(
+        &self,
+        mode: &NetlinkInterfaceMode,
+        others: &[NetlinkInterfaceMode],
+    ) -> Result<(), WirelessRefusal> {
+        if !self.supported_modes().contains(mode) {
+            return Err(WirelessRefusal::UnsupportedMode);
+        }
+
+        // Software modes and lone interfaces are always allowed
+        let software = self.software_modes();
+        if software.contains(mode) || others.is_empty() {
+            return Ok(());
+        }
+
+        let mut modes = others.to_vec();
+        modes.push(mode.clone());
+        match self
+            .interface_combinations
+            .iter()
+            .any(|x| x.allows(&modes, &software))
+        {
+            true => Ok(()),
+            false => Err(WirelessRefusal::UnsupportedCombination),
+        }
+    }
 }
 
+// Reasons for a wireless device to turn down an interface
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WirelessRefusal {
+    // The device can't run interfaces of the requested mode at all
+    UnsupportedMode,
+    // The device can't run the interface next to the ones it already has
+    UnsupportedCombination,
+    // The channel isn't supported, or not allowed in the regulatory domain
+    UnsupportedChannel,
+    // New interfaces are created on top of an existing one of the device
+    LastInterface,
+    // The channel of an operating interface, e.g. an AP started by hostapd,
+    // can only be switched by whatever runs it
+    InterfaceOperating,
+}
+
+impl fmt::Display for WirelessRefusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedMode => write!(f, "Interface mode is not supported by the device"),
+            Self::UnsupportedCombination => {
+                write!(f, "Interface combination is not supported by the device")
+            }
+            Self::UnsupportedChannel => write!(f, "Channel is not usable by the device"),
+            Self::LastInterface => write!(f, "Interface is the last one of the device"),
+            Self::InterfaceOperating => write!(f, "Interface is operating on its channel"),
+        }
+    }
+}
+
+impl std::error::Error for WirelessRefusal {}
+
+// Attributes and values that wl-nl80211 doesn't know of
+const NL80211_ATTR_MGMT_SUBTYPE: u16 = 41;
+const NL80211_ATTR_REASON_CODE: u16 = 54;
+const NL80211_ATTR_WIPHY_TX_POWER_SETTING: u16 = 97;
+const NL80211_TX_POWER_AUTOMATIC: u32 = 0;
+const NL80211_TX_POWER_LIMITED: u32 = 1;
+const NL80211_TX_POWER_FIXED: u32 = 2;
+// Subtype of deauthentication frames, as opposed to disassociation ones
+const IEEE80211_STYPE_DEAUTH: u8 = 12;
+// Default reason for deauthenticating stations: their previous
+// authentication is no longer valid
+pub const DEAUTH_REASON_CODE: u16 = 2;
+
+// nl80211 multicast groups that carry interface, device and station changes,
+// and scan completions
+const NL80211_EVENT_GROUPS: [&str; 4] = ["config", "mlme", "scan", "regulatory"];
+
+// How long a requested regulatory domain is waited for before reporting
+// whatever the kernel has applied by then
+const REGULATORY_CHANGE_WAIT: Duration = Duration::from_secs(2);
+
 pub struct WiphyManager {
-    nl80211: Nl80211Handle,
-    nl80211_future: JoinHandle<()>,
+    nl80211: SupervisedHandle<Nl80211Handle>,
+    // Scans wait for their completion to be notified
+    events: broadcast::Sender<NetlinkEvent>,
 }
 
 impl WiphyManager {
-    pub fn try_new() -> Result<Self> {
-        let (connection, nl80211, _) = wl_nl80211::new_connection()?;
-        let nl80211_future = tokio::spawn(connection);
-        Ok(Self {
-            nl80211,
-            nl80211_future,
+    // Connects to nl80211 and listens to its notifications, which are parsed
+    // and sent through `events`
+    pub async fn try_new_with_events(
+        netns: Option<&str>,
+        events: broadcast::Sender<NetlinkEvent>,
+    ) -> Result<Self> {
+        let health = Arc::new(HealthMonitor::new());
+        let netns = netns.map(str::to_owned);
+        let connection =
+            Self::connect_with_events(netns.clone(), events.clone(), health.clone()).await?;
+
+        let nl80211 = SupervisedHandle::new(
+            "nl80211",
+            connection,
+            health.clone(),
+            Some(events.clone()),
+            {
+                let events = events.clone();
+                move || {
+                    Box::pin(Self::connect_with_events(
+                        netns.clone(),
+                        events.clone(),
+                        health.clone(),
+                    ))
+                }
+            },
+        );
+
+        Ok(Self { nl80211, events })
+    }
+
+    fn connect(netns: Option<&str>) -> Result<Connection<Nl80211Handle>> {
+        let (connection, handle, _) = enter_namespace(netns, wl_nl80211::new_connection)?;
+        Ok(Connection {
+            handle,
+            futures: vec![Box::pin(connection)],
         })
     }
-    pub async fn get_wiphy_interfaces(&self) -> Result<Vec<WiphyInterface>> {
-        let mut interfaces = vec![];
-        let mut interface = self.nl80211.interface().get(Vec::new()).execute().await;
-        while let Some(msg) = interface.try_next().await? {
-            let mut index = None;
-            let mut phy_index = None;
-            let mut name = None;
-            let mut iftype = None;
-            for attr in msg.payload.attributes.into_iter() {
-                match attr {
-                    Nl80211Attr::IfIndex(i) => {
-                        index = Some(i);
+
+    async fn connect_with_events(
+        netns: Option<String>,
+        events: broadcast::Sender<NetlinkEvent>,
+        health: Arc<HealthMonitor>,
+    ) -> Result<Connection<Nl80211Handle>> {
+        let mut connection = Self::connect(netns.as_deref())?;
+
+        // Notifications are received on a separate socket, which needs to
+        // join the nl80211 multicast groups before it is polled
+        let (mut events_connection, _, mut messages) =
+            enter_namespace(netns.as_deref(), wl_nl80211::new_connection)?;
+        let group_ids = Self::resolve_multicast_groups(netns.as_deref()).await?;
+        for group_id in group_ids {
+            events_connection
+                .socket_mut()
+                .socket_mut()
+                .add_membership(group_id)?;
+        }
+
+        let messages_future = async move {
+            while let Some((message, _)) = messages.next().await {
+                let message = match message.payload {
+                    NetlinkPayload::InnerMessage(message) => message,
+                    // The socket buffer was full and notifications were dropped
+                    NetlinkPayload::Overrun(_) => {
+                        health.overrun();
+                        let _ = events.send(NetlinkEvent::Overrun);
+                        continue;
                     }
-                    Nl80211Attr::Wiphy(i) => {
-                        phy_index = Some(i);
+                    _ => continue,
+                };
+
+                let message = match message.parse_into_genlmsg::<Nl80211Message>() {
+                    Ok(message) => message.payload,
+                    Err(e) => {
+                        log::warn!("Failed to parse nl80211 event: {}", e);
+                        continue;
+                    }
+                };
+
+                let Some(event) = Self::parse_event(message) else {
+                    continue;
+                };
+
+                log::trace!("Received nl80211 event: {:?}", event);
+                // Sending only fails when nobody is subscribed, which is fine
+                let _ = events.send(event);
+            }
+
+            log::warn!("nl80211 event stream has ended");
+        };
+
+        connection.futures.push(Box::pin(events_connection));
+        connection.futures.push(Box::pin(messages_future));
+        Ok(connection)
+    }
+
+    // Multicast group IDs are resolved through a short-lived connection,
+    // which is only polled until the response arrives
+    async fn resolve_multicast_groups(netns: Option<&str>) -> Result<Vec<u32>> {
+        let (connection, nl80211, _) = enter_namespace(netns, wl_nl80211::new_connection)?;
+
+        let mut message = NetlinkMessage::from(GenlMessage::from_payload(GenlCtrl {
+            cmd: GenlCtrlCmd::GetFamily,
+            nlas: vec![GenlCtrlAttrs::FamilyName(
+                Nl80211Message::family_name().to_owned(),
+            )],
+        }));
+        message.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+
+        let request = async move {
+            let mut group_ids = vec![];
+            let mut responses = nl80211.handle.clone().request(message).await?;
+            while let Some(response) = responses.next().await {
+                let NetlinkPayload::InnerMessage(response) = response?.payload else {
+                    continue;
+                };
+
+                for nla in response.payload.nlas {
+                    let GenlCtrlAttrs::McastGroups(groups) = nla else {
+                        continue;
+                    };
+
+                    for group in groups {
+                        let mut name = None;
+                        let mut id = None;
+                        for attr in group {
+                            match attr {
+                                McastGrpAttrs::Name(s) => name = Some(s),
+                                McastGrpAttrs::Id(i) => id = Some(i),
+                            }
+                        }
+
+                        if let (Some(name), Some(id)) = (name, id)
+                            && NL80211_EVENT_GROUPS.contains(&name.as_str())
+                        {
+                            log::debug!("Resolved nl80211 multicast group '{}': {}", name, id);
+                            group_ids.push(id);
+                        }
                     }
-                    Nl80211Attr::IfName(s) => {
-                        name = Some(s);
+                }
+            }
+
+            Ok(group_ids)
+        };
+
+        tokio::select! {
+            result = request => result,
+            _ = connection => Err(anyhow!("nl80211 connection ended while resolving multicast groups")),
+        }
+    }
+
+    // The handle changes whenever the connection is reestablished, so it
+    // must not be kept around
+    fn handle(&self) -> Nl80211Handle {
+        self.nl80211.get()
+    }
+
+    pub fn health(&self) -> ConnectionHealth {
+        self.nl80211.health()
+    }
+
+    fn parse_event(message: Nl80211Message) -> Option<NetlinkEvent> {
+        match message.cmd {
+            Nl80211Command::NewInterface | Nl80211Command::SetInterface => {
+                Self::parse_interface(message.attributes)
+                    .map(NetlinkEvent::WirelessInterfaceUpdated)
+            }
+            Nl80211Command::DelInterface => Self::parse_interface(message.attributes)
+                .map(NetlinkEvent::WirelessInterfaceRemoved),
+            Nl80211Command::NewWiphy | Nl80211Command::DelWiphy => {
+                message.attributes.into_iter().find_map(|attr| match attr {
+                    Nl80211Attr::Wiphy(phy_index) => {
+                        Some(NetlinkEvent::WirelessDeviceChanged { phy_index })
                     }
-                    Nl80211Attr::IfType(t) => {
-                        iftype = Some(t);
+                    _ => None,
+                })
+            }
+            Nl80211Command::NewScanResults | Nl80211Command::ScanAborted => {
+                message.attributes.iter().find_map(|attr| match attr {
+                    Nl80211Attr::IfIndex(index) => Some(NetlinkEvent::ScanFinished {
+                        index: *index,
+                        aborted: message.cmd == Nl80211Command::ScanAborted,
+                    }),
+                    _ => None,
+                })
+            }
+            // Changes of a single device's own domain aren't of interest
+            Nl80211Command::RegChange => Some(NetlinkEvent::RegulatoryChanged {
+                country: NetlinkRegulatoryDomain::parse(&message.attributes).map(|x| x.country),
+            }),
+            Nl80211Command::NewStation | Nl80211Command::DelStation => {
+                let mut index = None;
+                let mut mac_address = None;
+                for attr in message.attributes {
+                    match attr {
+                        Nl80211Attr::IfIndex(i) => index = Some(i),
+                        Nl80211Attr::Mac(mac) => mac_address = Some(MacAddr::from(mac)),
+                        _ => {}
                     }
-                    _ => {}
                 }
+
+                let (index, mac_address) = (index?, mac_address?);
+                Some(match message.cmd {
+                    Nl80211Command::NewStation => {
+                        NetlinkEvent::StationConnected { index, mac_address }
+                    }
+                    _ => NetlinkEvent::StationDisconnected { index, mac_address },
+                })
             }
-            let (Some(index), Some(phy_index), Some(name), Some(iftype)) =
-                (index, phy_index, name, iftype)
-            else {
-                log::warn!("Missing required field in wiphy interface.");
+            _ => None,
+        }
+    }
+
+    pub async fn get_wiphy_interfaces(&self) -> Result<Vec<WiphyInterface>> {
+        let mut interfaces = vec![];
+        let mut interface = self.handle().interface().get(Vec::new()).execute().await;
+        while let Some(msg) = interface.try_next().await? {
+            let Some(wiphy_interface) = Self::parse_interface(msg.payload.attributes) else {
                 continue;
             };
-            interfaces.push(WiphyInterface {
-                index,
-                phy_index,
-                name,
-                iftype,
-            })
+            interfaces.push(wiphy_interface);
         }
 
         Ok(interfaces)
     }
 
+    fn parse_interface(attributes: Vec<Nl80211Attr>) -> Option<WiphyInterface> {
+        let mut index = None;
+        let mut phy_index = None;
+        let mut name = None;
+        let mut iftype = None;
+        let mut use_4addr = false;
+        let mut frequency = None;
+        let mut width = None;
+        let mut center_frequency = None;
+        let mut center_frequency2 = None;
+        let mut tx_power = None;
+        for attr in attributes.into_iter() {
+            match attr {
+                Nl80211Attr::IfIndex(i) => {
+                    index = Some(i);
+                }
+                Nl80211Attr::Wiphy(i) => {
+                    phy_index = Some(i);
+                }
+                Nl80211Attr::IfName(s) => {
+                    name = Some(s);
+                }
+                Nl80211Attr::IfType(t) => {
+                    iftype = Some(t);
+                }
+                Nl80211Attr::Use4Addr(enabled) => {
+                    use_4addr = enabled;
+                }
+                Nl80211Attr::WiphyFreq(f) => {
+                    frequency = Some(f);
+                }
+                Nl80211Attr::ChannelWidth(w) => {
+                    width = NetlinkChannelWidth::from_nl80211(w);
+                }
+                Nl80211Attr::CenterFreq1(f) => {
+                    center_frequency = Some(f);
+                }
+                Nl80211Attr::CenterFreq2(f) => {
+                    center_frequency2 = Some(f);
+                }
+                Nl80211Attr::WiphyTxPowerLevel(level) => {
+                    tx_power = Some(level);
+                }
+                _ => {}
+            }
+        }
+        let (Some(index), Some(phy_index), Some(name), Some(iftype)) =
+            (index, phy_index, name, iftype)
+        else {
+            log::warn!("Missing required field in wiphy interface.");
+            return None;
+        };
+
+        let channel = frequency.map(|frequency| NetlinkChannel {
+            frequency,
+            channel: frequency_to_channel(frequency),
+            width: width.unwrap_or(NetlinkChannelWidth::NoHt20),
+            center_frequency,
+            center_frequency2,
+        });
+
+        Some(WiphyInterface {
+            index,
+            phy_index,
+            name,
+            iftype,
+            use_4addr,
+            channel,
+            tx_power,
+        })
+    }
+
     pub async fn get_wiphy_devices(&self) -> Result<Vec<WiphyDevice>> {
-        let mut wiphy = self.nl80211.wireless_physic().get().execute().await;
+        let mut wiphy = self.handle().wireless_physic().get().execute().await;
         let mut devices = HashMap::new();
         while let Some(msg) = wiphy.try_next().await? {
             let mut phy_index = None;
             let mut phy_name = None;
             let mut supported_iftypes = None;
+            let mut software_iftypes = None;
+            let mut interface_combinations = None;
+            let mut bands = vec![];
+            let mut cipher_suites = None;
             for attr in msg.payload.attributes.into_iter() {
                 match attr {
                     wl_nl80211::Nl80211Attr::Wiphy(index) => {
@@ -97,6 +479,20 @@ impl WiphyManager {
                     wl_nl80211::Nl80211Attr::SupportedIftypes(iftypes) => {
                         supported_iftypes = Some(iftypes);
                     }
+                    wl_nl80211::Nl80211Attr::SoftwareIftypes(iftypes) => {
+                        software_iftypes = Some(iftypes);
+                    }
+                    wl_nl80211::Nl80211Attr::InterfaceCombination(combinations) => {
+                        interface_combinations = Some(combinations);
+                    }
+                    // Split dumps carry a few bands, or parts of one, at a
+                    // time
+                    wl_nl80211::Nl80211Attr::WiphyBands(band_list) => {
+                        bands.extend(band_list);
+                    }
+                    wl_nl80211::Nl80211Attr::CipherSuites(suites) => {
+                        cipher_suites = Some(suites);
+                    }
 
                     _ => {}
                 }
@@ -111,6 +507,10 @@ impl WiphyManager {
                 phy_index,
                 phy_name: "".to_string(),
                 supported_iftypes: vec![],
+                software_iftypes: vec![],
+                interface_combinations: vec![],
+                bands: vec![],
+                cipher_suites: vec![],
             });
 
             if let Some(phy_name) = phy_name {
@@ -121,12 +521,163 @@ impl WiphyManager {
                 wiphy_dev.supported_iftypes = supported_iftypes;
             }
 
+            if let Some(software_iftypes) = software_iftypes {
+                wiphy_dev.software_iftypes = software_iftypes;
+            }
+
+            if let Some(interface_combinations) = interface_combinations {
+                wiphy_dev.interface_combinations = interface_combinations
+                    .into_iter()
+                    .map(NetlinkInterfaceCombination::from)
+                    .collect();
+            }
+
+            if let Some(cipher_suites) = cipher_suites {
+                wiphy_dev.cipher_suites = cipher_suites;
+            }
+
+            wiphy_dev.bands.extend(bands);
+
             devices.insert(phy_index, wiphy_dev);
         }
 
         Ok(devices.into_values().collect())
     }
 
+    // cfg80211 answers with EBUSY when the interfaces of a device would no
+    // longer fit any of its interface combinations
+    fn map_refusal(error: Nl80211Error) -> anyhow::Error {
+        match &error {
+            Nl80211Error::NetlinkError(e) if e.to_io().raw_os_error() == Some(libc::EBUSY) => {
+                anyhow::Error::new(error).context(WirelessRefusal::UnsupportedCombination)
+            }
+            _ => error.into(),
+        }
+    }
+
+    // cfg80211 answers with EBUSY when the channel of an interface that
+    // operates on one is set directly, rather than through a channel switch
+    // announcement by hostapd or the like
+    fn map_channel_refusal(error: Nl80211Error) -> anyhow::Error {
+        match &error {
+            Nl80211Error::NetlinkError(e) if e.to_io().raw_os_error() == Some(libc::EBUSY) => {
+                anyhow::Error::new(error).context(WirelessRefusal::InterfaceOperating)
+            }
+            _ => error.into(),
+        }
+    }
+
+    // Scans on `ssids` (all of them if empty) and `frequencies` (all of the
+    // supported ones if empty), and returns every BSS known after it. A scan
+    // that is already running is waited for instead.
+    pub async fn scan(
+        &self,
+        wiphy_interface: &WiphyInterface,
+        ssids: Vec<String>,
+        frequencies: Vec<u32>,
+    ) -> Result<Vec<NetlinkBss>> {
+        // Subscribed before triggering, so that the completion can't be missed
+        let mut events = self.events.subscribe();
+
+        let mut scan = Nl80211Scan::new(wiphy_interface.index);
+        if !ssids.is_empty() {
+            scan = scan.ssids(ssids);
+        }
+        if !frequencies.is_empty() {
+            scan = scan.scan_frequencies(frequencies);
+        }
+        // Access points need to be told that they may go off channel
+        if wiphy_interface.iftype == Nl80211InterfaceType::Ap {
+            scan = scan.scan_flags(Nl80211ScanFlags::Ap);
+        }
+
+        let mut result = self.handle().scan().trigger(scan.build()).execute().await;
+        match result.try_next().await {
+            Ok(_) => {}
+            Err(Nl80211Error::NetlinkError(e)) if e.to_io().raw_os_error() == Some(libc::EBUSY) => {
+                log::debug!(
+                    "Scan already running on interface '{}'",
+                    wiphy_interface.name
+                );
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        loop {
+            match events.recv().await {
+                Ok(NetlinkEvent::ScanFinished { index, aborted })
+                    if index == wiphy_interface.index =>
+                {
+                    if aborted {
+                        return Err(anyhow!("Scan was aborted"));
+                    }
+                    break;
+                }
+                // The completion may have been missed, the results are as
+                // recent as they get
+                Ok(NetlinkEvent::Overrun | NetlinkEvent::Reconnected)
+                | Err(broadcast::error::RecvError::Lagged(_)) => {
+                    log::debug!(
+                        "Scan completion on interface '{}' may have been missed",
+                        wiphy_interface.name
+                    );
+                    break;
+                }
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Closed) => {
+                    return Err(anyhow!("nl80211 event stream has ended"));
+                }
+            }
+        }
+
+        self.get_scan_results(wiphy_interface.index).await
+    }
+
+    pub async fn get_scan_results(&self, index: u32) -> Result<Vec<NetlinkBss>> {
+        let mut dump = self.handle().scan().dump(index).execute().await;
+        let mut results = vec![];
+        while let Some(msg) = dump.try_next().await? {
+            for attr in msg.payload.attributes {
+                if let Nl80211Attr::Bss(attributes) = attr
+                    && let Some(bss) = NetlinkBss::parse(attributes)
+                {
+                    results.push(bss);
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    pub async fn get_survey(&self, index: u32) -> Result<Vec<ChannelSurvey>> {
+        let mut dump = self
+            .handle()
+            .survey()
+            .dump(Nl80211Survey::new(index).build())
+            .execute()
+            .await;
+        let mut surveys = vec![];
+        while let Some(msg) = dump.try_next().await? {
+            if let Some(survey) = ChannelSurvey::parse(msg.payload.attributes) {
+                surveys.push(survey);
+            }
+        }
+
+        Ok(surveys)
+    }
+
+    pub async fn get_stations(&self, index: u32) -> Result<Vec<NetlinkStation>> {
+        let mut dump = self.handle().station().dump(index).execute().await;
+        let mut stations = vec![];
+        while let Some(msg) = dump.try_next().await? {
+            if let Some(station) = NetlinkStation::parse(msg.payload.attributes) {
+                stations.push(station);
+            }
+        }
+
+        Ok(stations)
+    }
+
     pub async fn set_wiphy_interface_mode(
         &self,
         wiphy_interface: &WiphyInterface,
@@ -136,7 +687,63 @@ impl WiphyManager {
             .if_index(wiphy_interface.index)
             .interface_type(iftype)
             .build();
-        let mut result = self.nl80211.interface().set(attrs).execute().await;
+        let mut result = self.handle().interface().set(attrs).execute().await;
+        result.try_next().await.map_err(Self::map_refusal)?;
+        Ok(())
+    }
+
+    pub async fn set_wiphy_channel(&self, index: u32, channel: &NetlinkChannel) -> Result<()> {
+        let mut attrs = Nl80211Channel::new(index)
+            .frequency(channel.frequency)
+            .channel_width(channel.width.into());
+        if let Some(center_frequency) = channel.center_frequency {
+            attrs = attrs.center_frequency(center_frequency);
+        }
+        let mut attrs = attrs.build();
+        if let Some(center_frequency2) = channel.center_frequency2 {
+            attrs.push(Nl80211Attr::CenterFreq2(center_frequency2));
+        }
+
+        let mut result = self.handle().wireless_physic().set(attrs).execute().await;
+        result.try_next().await.map_err(Self::map_channel_refusal)?;
+        Ok(())
+    }
+
+    // Applies to the interface alone if the driver supports it, and to its
+    // physical device otherwise
+    pub async fn set_wiphy_tx_power(&self, index: u32, tx_power: NetlinkTxPower) -> Result<()> {
+        let (setting, level) = match tx_power {
+            NetlinkTxPower::Auto => (NL80211_TX_POWER_AUTOMATIC, None),
+            NetlinkTxPower::Limited(level) => (NL80211_TX_POWER_LIMITED, Some(level)),
+            NetlinkTxPower::Fixed(level) => (NL80211_TX_POWER_FIXED, Some(level)),
+        };
+
+        let mut attrs = vec![
+            Nl80211Attr::IfIndex(index),
+            Nl80211Attr::Other(DefaultNla::new(
+                NL80211_ATTR_WIPHY_TX_POWER_SETTING,
+                setting.to_ne_bytes().to_vec(),
+            )),
+        ];
+        if let Some(level) = level {
+            attrs.push(Nl80211Attr::WiphyTxPowerLevel(level));
+        }
+
+        let mut result = self.handle().wireless_physic().set(attrs).execute().await;
+        result.try_next().await?;
+        Ok(())
+    }
+
+    pub async fn set_wiphy_interface_4addr(
+        &self,
+        wiphy_interface: &WiphyInterface,
+        enabled: bool,
+    ) -> Result<()> {
+        let attrs = wl_nl80211::Nl80211AttrsBuilder::<Nl80211Interface>::new()
+            .if_index(wiphy_interface.index)
+            .replace(Nl80211Attr::Use4Addr(enabled))
+            .build();
+        let mut result = self.handle().interface().set(attrs).execute().await;
         result.try_next().await?;
         Ok(())
     }
@@ -147,19 +754,20 @@ impl WiphyManager {
         iftype: Nl80211InterfaceType,
         name: String,
     ) -> Result<()> {
-        self.nl80211
+        self.handle()
             .interface()
             .add(Nl80211NewInterface::new(wiphy_dev.phy_index, iftype, name).build())
             .execute()
             .await
             .try_next()
-            .await?;
+            .await
+            .map_err(Self::map_refusal)?;
 
         Ok(())
     }
 
     pub async fn delete_wiphy_interface(&self, wiphy_iface: &WiphyInterface) -> Result<()> {
-        self.nl80211
+        self.handle()
             .interface()
             .delete(Nl80211Interface::new(wiphy_iface.index).build())
             .execute()
@@ -169,10 +777,121 @@ impl WiphyManager {
 
         Ok(())
     }
-}
 
-impl Drop for WiphyManager {
-    fn drop(&mut self) {
-        self.nl80211_future.abort();
+    // The domain all devices follow, unless they manage their own
+    pub async fn get_regulatory_domain(&self) -> Result<NetlinkRegulatoryDomain> {
+        let mut message = NetlinkMessage::from(GenlMessage::from_payload(Nl80211Message {
+            cmd: Nl80211Command::GetReg,
+            attributes: vec![],
+        }));
+        message.header.flags = NLM_F_REQUEST;
+
+        let mut responses = self.handle().request(message).await?;
+        while let Some(response) = responses.next().await {
+            match response?.payload {
+                NetlinkPayload::InnerMessage(msg) => {
+                    if let Some(domain) = NetlinkRegulatoryDomain::parse(&msg.payload.attributes) {
+                        return Ok(domain);
+                    }
+                }
+                NetlinkPayload::Error(e) if e.code.is_some() => {
+                    return Err(Nl80211Error::NetlinkError(e).into());
+                }
+                _ => {}
+            }
+        }
+
+        Err(anyhow!("No regulatory domain was reported"))
+    }
+
+    // Requests a new country from the kernel, which applies it
+    // asynchronously. The kernel may apply something else, e.g. when the
+    // request is intersected with the country of an associated AP, or
+    // nothing at all, so the change is only waited for a short while.
+    pub async fn set_regulatory_country(&self, country: &str) -> Result<()> {
+        // Subscribed before requesting, so that the change can't be missed
+        let mut events = self.events.subscribe();
+
+        let mut alpha2 = country.as_bytes().to_vec();
+        alpha2.push(0);
+        let mut message = NetlinkMessage::from(GenlMessage::from_payload(Nl80211Message {
+            cmd: Nl80211Command::ReqSetReg,
+            attributes: vec![Nl80211Attr::Other(DefaultNla::new(
+                NL80211_ATTR_REG_ALPHA2,
+                alpha2,
+            ))],
+        }));
+        message.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+
+        let mut responses = self.handle().request(message).await?;
+        while let Some(response) = responses.next().await {
+            if let NetlinkPayload::Error(e) = response?.payload
+                && e.code.is_some()
+            {
+                return Err(Nl80211Error::NetlinkError(e).into());
+            }
+        }
+
+        // Requesting the current country again changes nothing, and sends
+        // no notification either
+        if self.get_regulatory_domain().await?.country == country {
+            return Ok(());
+        }
+
+        let changed = async {
+            loop {
+                match events.recv().await {
+                    Ok(NetlinkEvent::RegulatoryChanged { .. })
+                    | Err(broadcast::error::RecvError::Lagged(_))
+                    | Err(broadcast::error::RecvError::Closed) => break,
+                    Ok(_) => continue,
+                }
+            }
+        };
+        if tokio::time::timeout(REGULATORY_CHANGE_WAIT, changed)
+            .await
+            .is_err()
+        {
+            log::debug!("Regulatory domain didn't change to {} yet", country);
+        }
+        Ok(())
+    }
+
+    // Deauthenticates a station from an access point. wl-nl80211 has no
+    // request for it, so it is built by hand.
+    pub async fn del_station(
+        &self,
+        index: u32,
+        mac_address: MacAddr,
+        reason_code: u16,
+    ) -> Result<()> {
+        let attributes = vec![
+            Nl80211Attr::IfIndex(index),
+            Nl80211Attr::Mac(mac_address.as_bytes().try_into()?),
+            Nl80211Attr::Other(DefaultNla::new(
+                NL80211_ATTR_MGMT_SUBTYPE,
+                vec![IEEE80211_STYPE_DEAUTH],
+            )),
+            Nl80211Attr::Other(DefaultNla::new(
+                NL80211_ATTR_REASON_CODE,
+                reason_code.to_ne_bytes().to_vec(),
+            )),
+        ];
+        let mut message = NetlinkMessage::from(GenlMessage::from_payload(Nl80211Message {
+            cmd: Nl80211Command::DelStation,
+            attributes,
+        }));
+        message.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+
+        let mut responses = self.handle().request(message).await?;
+        while let Some(response) = responses.next().await {
+            if let NetlinkPayload::Error(e) = response?.payload
+                && e.code.is_some()
+            {
+                return Err(Nl80211Error::NetlinkError(e).into());
+            }
+        }
+
+        Ok(())
     }
 }